@@ -0,0 +1,62 @@
+// Copyright (C) 2023 by BorisBoutillier
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+// A variant of the background.wgsl star nest, swirled around a dark core.
+// Adapted from https://github.com/BorisBoutillier/Kataster
+// This shader is inspired by Star Nest by Pablo Roman Andrioli:
+// https://www.shadertoy.com/view/XlfGRj
+
+#import bevy_pbr::mesh_view_bindings globals
+#import bevy_pbr::forward_io VertexOutput
+
+const iterations = 8;
+const formuparam = 0.53;
+
+const volsteps = 8;
+const stepsize = 0.1;
+
+const tile = 0.85;
+const speed = 0.5;
+
+const brightness = 0.0015;
+const distfading = 0.730;
+const saturation = 0.850;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.uv * 2.0 - 1.0;
+    let radius = length(uv);
+    // swirl the stars around the center, faster near the core
+    let angle = atan2(uv.y, uv.x) + globals.time * speed + 0.5 / (radius + 0.1);
+    let dir = vec3<f32>(cos(angle) * radius, sin(angle) * radius, 1.0);
+    let from_ = vec3<f32>(1.0, 0.5, 0.5);
+
+    // volumetric rendering
+    var s = 0.1;
+    var fade = 1.0;
+    var v = vec3<f32>(0.);
+    for (var r = 0; r < volsteps; r = r + 1) {
+        var p = from_ + s * dir * 0.5;
+        p = abs(vec3<f32>(tile) - (p % vec3<f32>(tile * 2.0)));
+
+        var pa = 0.0;
+        var a = 0.0;
+        for (var i = 0; i < iterations; i = i + 1) {
+            p = abs(p) / dot(p, p) - formuparam; // the magic formula
+            a = a + abs(length(p) - pa); // absolute sum of average change
+            pa = length(p);
+        }
+
+        a = a * a * a; // add contrast
+        v = v + fade;
+        v = v + vec3<f32>(s * s * s * s, s * s, s) * a * brightness * fade; // bluish coloring
+        fade = fade * distfading; // distance fading;
+        s = s + stepsize;
+    }
+    v = mix(vec3<f32>(length(v)), v, saturation); // color_adjust
+
+    // the event horizon is dark, surrounded by a bright accretion ring fading out at the edge.
+    let ring = smoothstep(0.3, 0.4, radius) * (1.0 - smoothstep(0.4, 1.0, radius));
+    let alpha = 1.0 - smoothstep(0.8, 1.0, radius);
+    return vec4<f32>(v * 0.003 * ring, alpha);
+}
@@ -15,9 +15,12 @@ pub enum AppStatus {
     Splash,
     Menu,
     SelectLevel,
+    Settings,
     Playing,
     Completed,
     Paused,
+    /// Playing frame by frame, see the tas module.
+    Tas,
 }
 
 #[derive(Component)]
@@ -40,6 +43,7 @@ impl Plugin for Plug {
             .add_plugins((
                 splash::Plug,
                 select::Plug,
+                settings::Plug,
                 menu::Plug,
                 pause::Plug,
                 completed::Plug,
@@ -50,8 +54,17 @@ impl Plugin for Plug {
 #[derive(Component)]
 pub enum MenuAction {
     Restart,
+    RespawnCheckpoint,
+    ExportReplay,
+    ImportReplay,
+    ToggleTas,
+    ToggleGhost(GhostKind),
+    Script(Script),
     SelectMenu(AppStatus),
     LoadLevel(usize),
+    SetDifficulty(Difficulty),
+    NextStickDeadzone,
+    NextStickCurve,
     Quit,
 }
 
@@ -66,6 +79,7 @@ fn handle_nav_requests(
             let app_status = app_status.get();
             let next_status = match *app_status {
                 AppStatus::SelectLevel => Some(AppStatus::Menu),
+                AppStatus::Settings => Some(AppStatus::Menu),
                 AppStatus::Completed => Some(AppStatus::Menu),
                 AppStatus::Paused => Some(AppStatus::Menu),
                 AppStatus::Menu => {
@@ -81,6 +95,7 @@ fn handle_nav_requests(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_nav_events(
     mut buttons: Query<&mut MenuAction>,
     mut events: EventReader<NavEvent>,
@@ -88,15 +103,45 @@ fn handle_nav_events(
     mut next_app_status: ResMut<NextState<AppStatus>>,
     mut next_game_status: ResMut<NextState<GameStatus>>,
     mut resources: ResMut<crate::resources::GameResources>,
+    mut difficulty: ResMut<Difficulty>,
+    mut export_event: EventWriter<ExportReplay>,
+    mut import_event: EventWriter<ImportReplay>,
+    mut script_event: EventWriter<Script>,
+    mut tas: ResMut<Tas>,
+    mut stick_settings: ResMut<StickSettings>,
 ) {
     events.nav_iter().activated_in_query_foreach_mut(
         &mut buttons,
         |mut button| match &mut *button {
             MenuAction::Quit => app_exit_events.send(bevy::app::AppExit),
             MenuAction::Restart => {
+                next_app_status.set(tas.playing_status());
+                next_game_status.set(GameStatus::Spawning);
+            }
+            MenuAction::RespawnCheckpoint => {
+                resources.respawn_at_checkpoint = true;
                 next_app_status.set(AppStatus::Playing);
                 next_game_status.set(GameStatus::Spawning);
             }
+            MenuAction::ExportReplay => {
+                export_event.send(ExportReplay);
+            }
+            // The menu stays open to show the result of the import.
+            MenuAction::ImportReplay => import_event.send(ImportReplay),
+            MenuAction::ToggleGhost(kind) => resources.ghosts.toggle(*kind),
+            MenuAction::Script(action) => {
+                script_event.send(*action);
+                next_app_status.set(tas.playing_status());
+            }
+            MenuAction::ToggleTas => {
+                *tas = Tas {
+                    enabled: !tas.enabled,
+                    ..default()
+                };
+                info!("TAS mode: {}", tas.enabled);
+                next_app_status.set(tas.playing_status());
+                next_game_status.set(GameStatus::Spawning);
+            }
             MenuAction::SelectMenu(app_status) => {
                 next_app_status.set(*app_status);
                 // despawn level?
@@ -104,12 +149,24 @@ fn handle_nav_events(
             MenuAction::LoadLevel(pos) => {
                 info!("Loading level {}", pos);
                 resources.current_level = *pos;
-                resources.ghost = None;
+                resources.ghosts.clear();
                 resources.thrust_history.clear();
+                resources.checkpoint = None;
+                resources.failed_attempts = 0;
                 resources.made_highscore = false;
+                resources.made_fuel_record = false;
+                resources.made_tas_record = false;
+                tas.enabled = false;
                 next_app_status.set(AppStatus::Playing);
                 next_game_status.set(GameStatus::Spawning);
             }
+            MenuAction::SetDifficulty(new_difficulty) => {
+                info!("Setting difficulty {:?}", new_difficulty);
+                *difficulty = *new_difficulty;
+                next_app_status.set(AppStatus::Menu);
+            }
+            MenuAction::NextStickDeadzone => *stick_settings = stick_settings.next_deadzone(),
+            MenuAction::NextStickCurve => *stick_settings = stick_settings.next_exponent(),
         },
     );
 }
@@ -121,12 +178,17 @@ const P_W: ScanCode = ScanCode(80);
 const R: ScanCode = ScanCode(19);
 const R_W: ScanCode = ScanCode(82);
 
+const C: ScanCode = ScanCode(46);
+const C_W: ScanCode = ScanCode(67);
+
 fn handle_app_input(
     keyboard_input: Res<Input<ScanCode>>,
     app_status: Res<State<AppStatus>>,
     gamepad_input: Res<Input<GamepadButton>>,
     mut next_app_status: ResMut<NextState<AppStatus>>,
     mut next_game_status: ResMut<NextState<GameStatus>>,
+    mut resources: ResMut<crate::resources::GameResources>,
+    tas: Res<Tas>,
 ) {
     let app_status = *app_status.get();
     let pause_pressed_keyboard = keyboard_input
@@ -137,8 +199,8 @@ fn handle_app_input(
         .any(|gb| gb.button_type == GamepadButtonType::Start);
     if pause_pressed_keyboard || start_pressed {
         if app_status == AppStatus::Paused {
-            next_app_status.set(AppStatus::Playing);
-        } else if app_status == AppStatus::Playing {
+            next_app_status.set(tas.playing_status());
+        } else if matches!(app_status, AppStatus::Playing | AppStatus::Tas) {
             next_app_status.set(AppStatus::Paused);
         };
     }
@@ -153,7 +215,7 @@ fn handle_app_input(
 
     if matches!(
         app_status,
-        AppStatus::Paused | AppStatus::Completed | AppStatus::Playing
+        AppStatus::Paused | AppStatus::Completed | AppStatus::Playing | AppStatus::Tas
     ) {
         let respawn_pressed_keyboard = keyboard_input
             .get_just_pressed()
@@ -162,13 +224,29 @@ fn handle_app_input(
             .get_just_pressed()
             .any(|gb| gb.button_type == GamepadButtonType::North);
         if respawn_pressed_keyboard || respawn_pressed {
+            next_game_status.set(GameStatus::Spawning);
+            next_app_status.set(tas.playing_status());
+        }
+
+        // Practice from the last checkpoint
+        let checkpoint_pressed_keyboard = keyboard_input
+            .get_just_pressed()
+            .any(|keycode| matches!(*keycode, C | C_W));
+        let checkpoint_pressed = gamepad_input
+            .get_just_pressed()
+            .any(|gb| gb.button_type == GamepadButtonType::West);
+        let can_respawn = resources.checkpoint.is_some() && !tas.enabled;
+        if (checkpoint_pressed_keyboard || checkpoint_pressed) && can_respawn {
+            resources.respawn_at_checkpoint = true;
             next_game_status.set(GameStatus::Spawning);
             next_app_status.set(AppStatus::Playing);
         }
     }
 }
 
+use crate::events::{ExportReplay, ImportReplay, Script};
 use crate::game_status::GameStatus;
+use crate::resources::{Difficulty, GhostKind, StickSettings, Tas};
 fn load_app_status_from_env(
     mut next_app_status: ResMut<NextState<AppStatus>>,
     mut next_game_status: ResMut<NextState<GameStatus>>,
@@ -296,6 +374,11 @@ mod menu {
                         MenuAction::SelectMenu(AppStatus::SelectLevel),
                     );
                 }
+                crate::ui::button::spawn_button(
+                    parent,
+                    "Settings",
+                    MenuAction::SelectMenu(AppStatus::Settings),
+                );
                 crate::ui::button::spawn_button(parent, "Quit", MenuAction::Quit);
             });
     }
@@ -307,6 +390,14 @@ mod pause {
     impl Plugin for Plug {
         fn build(&self, app: &mut App) {
             app.add_systems(OnEnter(AppStatus::Paused), crate::ui::pause::spawn)
+                .add_systems(
+                    Update,
+                    (
+                        crate::ui::pause::update_ghost_toggles,
+                        crate::ui::pause::update_import_status,
+                    )
+                        .run_if(in_state(AppStatus::Paused)),
+                )
                 .add_systems(OnExit(AppStatus::Paused), despawn);
         }
     }
@@ -326,6 +417,21 @@ mod select {
     }
 }
 
+mod settings {
+    use super::*;
+    pub struct Plug;
+    impl Plugin for Plug {
+        fn build(&self, app: &mut App) {
+            app.add_systems(OnEnter(AppStatus::Settings), crate::ui::settings::spawn)
+                .add_systems(
+                    Update,
+                    crate::ui::settings::update_stick_labels.run_if(in_state(AppStatus::Settings)),
+                )
+                .add_systems(OnExit(AppStatus::Settings), despawn);
+        }
+    }
+}
+
 mod completed {
     use super::*;
     pub struct Plug;
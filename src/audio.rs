@@ -8,14 +8,23 @@ use std::time::Duration;
 
 use crate::app_status::AppStatus;
 use crate::events;
+use crate::game_status::GameStatus;
+use crate::resources::GameResources;
 
 pub struct Plug;
 impl Plugin for Plug {
     fn build(&self, app: &mut App) {
         app.add_audio_source::<EngineNoise>()
+            .add_audio_source::<ExplosionNoise>()
             .add_systems(OnExit(AppStatus::Playing), silence_engine_noise)
+            .add_systems(OnExit(AppStatus::Tas), silence_engine_noise)
+            // The setup resets the thrust.
+            .add_systems(
+                OnEnter(GameStatus::Spawning),
+                resume_engine_noise.before(crate::level::setup),
+            )
             // .add_systems(Update, simple_input_system)
-            .add_systems(Update, update_engine_noise);
+            .add_systems(Update, (update_engine_noise, play_explosion));
     }
 }
 
@@ -69,12 +78,58 @@ impl Decodable for EngineNoise {
     }
 }
 
+/// A short burst of low noise fading out.
+#[derive(Asset, Debug, Clone, TypePath, Component)]
+pub struct ExplosionNoise;
+
+impl Decodable for ExplosionNoise {
+    type DecoderItem = f32;
+    type Decoder = rodio::source::TakeDuration<rodio::source::BltFilter<WhiteNoise>>;
+
+    fn decoder(&self) -> Self::Decoder {
+        let mut source = WhiteNoise::default()
+            .low_pass(200)
+            .take_duration(Duration::from_millis(600));
+        source.set_filter_fadeout();
+        source
+    }
+}
+
+fn play_explosion(
+    mut explosion_assets: ResMut<Assets<ExplosionNoise>>,
+    engine_noise_controller: Query<&AudioSink, With<EngineNoise>>,
+    mut events: EventReader<events::ShipDestroyed>,
+    mut commands: Commands,
+) {
+    for _event in events.read() {
+        if let Ok(sink) = engine_noise_controller.get_single() {
+            sink.pause()
+        }
+        commands.spawn(AudioSourceBundle {
+            source: explosion_assets.add(ExplosionNoise),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
+
 fn silence_engine_noise(engine_noise_controller: Query<&AudioSink, With<EngineNoise>>) {
     if let Ok(sink) = engine_noise_controller.get_single() {
         sink.pause()
     }
 }
 
+/// The engine is paused by the explosion, resume it when the thrust is held through the respawn.
+fn resume_engine_noise(
+    state: Res<GameResources>,
+    engine_noise_controller: Query<&AudioSink, With<EngineNoise>>,
+) {
+    if state.thrust != Vec2::ZERO {
+        if let Ok(sink) = engine_noise_controller.get_single() {
+            sink.play()
+        }
+    }
+}
+
 fn update_engine_noise(
     mut pitch_assets: ResMut<Assets<EngineNoise>>,
     engine_noise_controller: Query<&AudioSink, With<EngineNoise>>,
@@ -0,0 +1,37 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the black hole bundle, rendered with a variant of the background shader.
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+use crate::physics::BLACK_HOLE_RADIUS;
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct BlackHoleMaterial {}
+
+impl Material for BlackHoleMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "black_hole.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+pub fn new(
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<BlackHoleMaterial>>,
+    pos: Vec2,
+) -> MaterialMeshBundle<BlackHoleMaterial> {
+    // The shader draws the event horizon at 40% of the quad.
+    let size = BLACK_HOLE_RADIUS / 0.4 * 2.0;
+    MaterialMeshBundle {
+        mesh: meshes.add(shape::Quad::new([size, size].into()).into()),
+        material: materials.add(BlackHoleMaterial {}),
+        transform: Transform::from_translation(pos.extend(-0.1)),
+        ..default()
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the cargo crate bundle.
+
+use bevy::prelude::*;
+
+use crate::entities::ship::SHIP_RADIUS;
+use crate::physics::CRATE_SIZE;
+use crate::resources::GameResources;
+
+/// The index of the crate in the physics world.
+#[derive(Component)]
+pub struct Cargo(pub usize);
+
+#[derive(Bundle)]
+pub struct CargoBundle {
+    pub pbr: PbrBundle,
+}
+
+impl CargoBundle {
+    pub fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        position: Vec2,
+    ) -> Self {
+        Self {
+            pbr: PbrBundle {
+                mesh: meshes.add(shape::Cube { size: CRATE_SIZE }.into()),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::hex("#8b5a2b").unwrap(),
+                    perceptual_roughness: 1.0,
+                    ..default()
+                }),
+                transform: Transform::from_translation(position.extend(0.0)),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Stack the carried crates under the ship.
+pub fn update(state: Res<GameResources>, mut query: Query<(&Cargo, &mut Transform)>) {
+    let ship = &state.ship;
+    for (cargo, mut transform) in query.iter_mut() {
+        if ship.carries(cargo.0) {
+            // The crates are stacked in the order of their index.
+            let below = (ship.cargo & ((1 << cargo.0) - 1)).count_ones();
+            let offset = SHIP_RADIUS + CRATE_SIZE * (0.5 + below as f32);
+            transform.translation = (ship.pos - Vec2::new(0.0, offset)).extend(0.0);
+        }
+    }
+}
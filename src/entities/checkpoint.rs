@@ -0,0 +1,91 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the checkpoint bundle.
+
+use bevy::prelude::*;
+
+use crate::resources::GameResources;
+
+/// The index of the checkpoint in the level.
+#[derive(Component)]
+pub struct Checkpoint(pub usize);
+
+#[derive(Bundle)]
+pub struct CheckpointBundle {
+    pub pbr: PbrBundle,
+}
+
+pub const CHECKPOINT_SIZE: f32 = 0.15;
+
+impl Checkpoint {
+    pub fn reached(position: Vec2, ship: Vec2) -> bool {
+        (position - ship)
+            .abs()
+            .cmple(Vec2::splat(CHECKPOINT_SIZE))
+            .all()
+    }
+}
+
+#[derive(Resource)]
+pub struct CheckpointMaterials {
+    pub idle: Handle<StandardMaterial>,
+    pub active: Handle<StandardMaterial>,
+}
+impl FromWorld for CheckpointMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self {
+            idle: materials.add(StandardMaterial {
+                emissive: Color::rgb_linear(0.3, 0.3, 0.6),
+                ..default()
+            }),
+            active: materials.add(StandardMaterial {
+                emissive: Color::rgb_linear(0.5, 2.0, 4.0),
+                ..default()
+            }),
+        }
+    }
+}
+
+impl CheckpointBundle {
+    pub fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        material: &Handle<StandardMaterial>,
+        position: Vec2,
+    ) -> Self {
+        Self {
+            pbr: PbrBundle {
+                mesh: meshes.add(
+                    shape::Torus {
+                        radius: CHECKPOINT_SIZE,
+                        ring_radius: 0.01,
+                        ..default()
+                    }
+                    .into(),
+                ),
+                material: material.clone(),
+                // The torus is flat, rotate it to face the camera.
+                transform: Transform::from_translation(position.extend(0.0))
+                    .with_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Highlight the last touched checkpoint.
+pub fn update(
+    state: Res<GameResources>,
+    materials: Res<CheckpointMaterials>,
+    mut query: Query<(&Checkpoint, &mut Handle<StandardMaterial>)>,
+) {
+    let touched = state.checkpoint.as_ref().map(|checkpoint| checkpoint.idx);
+    for (checkpoint, mut material) in query.iter_mut() {
+        *material = if touched == Some(checkpoint.0) {
+            materials.active.clone()
+        } else {
+            materials.idle.clone()
+        };
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the explosion displayed when the ship is destroyed.
+
+use bevy::prelude::*;
+
+use crate::events::{Destruction, ShipDestroyed};
+use crate::physics::Crash;
+
+/// The explosion outlives the level respawn, it despawns itself when the timer finishes.
+#[derive(Component)]
+pub struct Explosion(Timer);
+
+const DURATION: f32 = 0.6;
+
+pub fn spawn(
+    mut commands: Commands,
+    mut events: EventReader<ShipDestroyed>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in events.read() {
+        let emissive = match event.cause {
+            Destruction::Crash(Crash::Impact) | Destruction::HardLanding => {
+                Color::rgb_linear(8.0, 4.0, 0.0)
+            }
+            Destruction::Crash(Crash::Trap) => Color::rgb_linear(8.0, 0.5, 0.0),
+        };
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(
+                    shape::UVSphere {
+                        radius: 0.1,
+                        ..default()
+                    }
+                    .into(),
+                ),
+                material: materials.add(StandardMaterial {
+                    emissive,
+                    ..default()
+                }),
+                transform: Transform::from_translation(event.pos.extend(0.1)),
+                ..default()
+            },
+            Explosion(Timer::from_seconds(DURATION, TimerMode::Once)),
+        ));
+    }
+}
+
+pub fn animate(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Explosion, &mut Transform)>,
+) {
+    for (entity, mut explosion, mut transform) in query.iter_mut() {
+        if explosion.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            let progress = explosion.0.percent();
+            transform.scale = Vec3::splat(1.0 + 3.0 * progress) * (1.0 - progress);
+        }
+    }
+}
@@ -1,8 +1,13 @@
 // Copyright (C) 2023 by Tristan de Cacqueray
 // SPDX-License-Identifier: MIT
 
+pub mod black_hole;
+pub mod cargo;
+pub mod checkpoint;
+pub mod explosion;
 pub mod goal;
 pub mod launch_pad;
+pub mod planet;
 pub mod ship;
 pub mod velocity_gizmo;
 pub mod wall;
@@ -0,0 +1,41 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the planet bundle.
+
+use bevy::prelude::*;
+
+use crate::physics::Planet;
+
+#[derive(Bundle)]
+pub struct PlanetBundle {
+    pub pbr: PbrBundle,
+}
+
+impl PlanetBundle {
+    pub fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        planet: &Planet,
+    ) -> Self {
+        Self {
+            pbr: PbrBundle {
+                mesh: meshes.add(
+                    shape::UVSphere {
+                        radius: planet.radius,
+                        sectors: 64,
+                        stacks: 32,
+                    }
+                    .into(),
+                ),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::hex("#9e9e9e").unwrap(),
+                    perceptual_roughness: 1.0,
+                    ..default()
+                }),
+                transform: Transform::from_translation(planet.center.extend(0.0)),
+                ..default()
+            },
+        }
+    }
+}
@@ -11,8 +11,9 @@ pub struct Velocity(pub Vec2);
 #[derive(Component)]
 pub struct Ship;
 
+/// The ship of a ghost run, see [Ghosts](crate::resources::Ghosts).
 #[derive(Component)]
-pub struct Ghost;
+pub struct Ghost(pub crate::resources::GhostKind);
 
 #[derive(Bundle)]
 pub struct ShipBundle {
@@ -27,6 +28,32 @@ impl Ship {
     pub fn size() -> Vec2 {
         [SHIP_SIZE, SHIP_SIZE].into()
     }
+
+    /// The center of the ship standing at the given position.
+    pub fn center(bottom: Vec2) -> Vec2 {
+        Vec2::new(bottom.x, bottom.y + SHIP_RADIUS)
+    }
+}
+
+/// A marker on the top of the ship to show its heading.
+pub fn nose(
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) -> PbrBundle {
+    PbrBundle {
+        mesh: meshes.add(
+            shape::Cube {
+                size: SHIP_SIZE / 3.0,
+            }
+            .into(),
+        ),
+        material: materials.add(StandardMaterial {
+            emissive: Color::rgb_linear(5.0, 1.0, 0.0),
+            ..default()
+        }),
+        transform: Transform::from_xyz(0.0, SHIP_RADIUS, 0.0),
+        ..default()
+    }
 }
 
 impl ShipBundle {
@@ -34,15 +61,15 @@ impl ShipBundle {
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<StandardMaterial>>,
         pos: Vec2,
-        color: f32,
+        emissive: Color,
     ) -> Self {
-        let translation = Vec3::new(pos.x, pos.y + SHIP_RADIUS, 0.0);
+        let translation = Ship::center(pos).extend(0.0);
         Self {
             vel: Velocity([0., 0.].into()),
             pbr: PbrBundle {
                 mesh: meshes.add(shape::Cube { size: SHIP_SIZE }.try_into().unwrap()),
                 material: materials.add(StandardMaterial {
-                    emissive: Color::rgb_linear(5.0 - color, 5.0 - color, 5.0),
+                    emissive,
                     metallic: 1.0,
                     perceptual_roughness: 0.0,
                     ..default()
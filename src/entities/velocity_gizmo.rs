@@ -11,15 +11,16 @@ use crate::entities::ship;
 pub struct VelocityGizmo;
 
 pub fn update_gizmo(
-    ship_query: Query<&ship::Velocity, With<ship::Ship>>,
-    mut gizmo_query: Query<&mut Transform, With<VelocityGizmo>>,
+    ship_query: Query<(&ship::Velocity, &Transform), With<ship::Ship>>,
+    mut gizmo_query: Query<&mut Transform, (With<VelocityGizmo>, Without<ship::Ship>)>,
 ) {
-    let ship = ship_query.single();
+    let (ship, ship_transform) = ship_query.single();
     let mut gizmo = gizmo_query.single_mut();
     let speed = ship.length();
     let angle = Vec2::new(0.0, 1.0).angle_between(ship.0);
 
-    gizmo.rotation = Quat::from_rotation_z(angle);
+    // The gizmo is attached to the ship, thus it needs to cancel the ship heading.
+    gizmo.rotation = ship_transform.rotation.inverse() * Quat::from_rotation_z(angle);
     gizmo.scale.y = speed * 20.0;
 }
 
@@ -5,18 +5,17 @@
 
 use bevy::prelude::*;
 
-#[derive(Debug, Component)]
-pub struct WallPosition {
-    pub translation: Vec3,
-    pub size: Vec2,
-}
+use crate::resources::GameResources;
 
 #[derive(Component)]
 pub struct Wall;
 
+/// The index of the moving wall in the physics world.
+#[derive(Component)]
+pub struct Mover(pub usize);
+
 #[derive(Bundle)]
 pub struct WallBundle {
-    pub pos: WallPosition,
     pub pbr: PbrBundle,
 }
 
@@ -30,6 +29,30 @@ impl WallBundle {
         })
     }
 
+    pub fn bumper_material(
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+    ) -> Handle<StandardMaterial> {
+        materials.add(StandardMaterial {
+            base_color: Color::hex("#f5a623").unwrap(),
+            emissive: Color::rgb_linear(1.5, 0.6, 0.0),
+            perceptual_roughness: 0.3,
+            metallic: 0.5,
+            ..default()
+        })
+    }
+
+    pub fn trap_material(
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+    ) -> Handle<StandardMaterial> {
+        materials.add(StandardMaterial {
+            base_color: Color::hex("#d0021b").unwrap(),
+            emissive: Color::rgb_linear(4.0, 0.0, 0.1),
+            perceptual_roughness: 1.0,
+            metallic: 0.0,
+            ..default()
+        })
+    }
+
     pub fn new(
         meshes: &mut ResMut<Assets<Mesh>>,
         material: &Handle<StandardMaterial>,
@@ -38,7 +61,6 @@ impl WallBundle {
     ) -> Self {
         let translation = pos.extend(0.0);
         Self {
-            pos: WallPosition { translation, size },
             pbr: PbrBundle {
                 mesh: meshes.add(shape::Box::new(size.x, size.y, 0.4).try_into().unwrap()),
                 material: material.clone(),
@@ -52,17 +74,11 @@ impl WallBundle {
     }
 }
 
-impl WallPosition {
-    pub fn top(&self) -> f32 {
-        self.translation.y + self.size.y / 2.0
-    }
-    pub fn bottom(&self) -> f32 {
-        self.translation.y - self.size.y / 2.0
-    }
-    pub fn left(&self) -> f32 {
-        self.translation.x - self.size.x / 2.0
-    }
-    pub fn right(&self) -> f32 {
-        self.translation.x + self.size.x / 2.0
+/// Move the walls to their position at the current frame.
+pub fn update_movers(state: Res<GameResources>, mut query: Query<(&Mover, &mut Transform)>) {
+    for (mover, mut transform) in query.iter_mut() {
+        if let Some(mover) = state.world.movers.get(mover.0) {
+            transform.translation = mover.at(state.ship.frame).center.extend(0.0);
+        }
     }
 }
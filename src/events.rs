@@ -11,6 +11,60 @@ pub struct NewHighscore {
     pub score: usize,
 }
 
+/// The level was completed with more fuel left than before.
+#[derive(Event)]
+pub struct NewFuelRecord {
+    pub level: usize,
+    pub fuel: usize,
+}
+
+/// The level was completed in TAS mode faster than before.
+#[derive(Event)]
+pub struct NewTasRecord {
+    pub level: usize,
+    pub score: usize,
+}
+
+/// The level was completed faster than the current ghost.
+#[derive(Event)]
+pub struct NewGhost {
+    pub level: usize,
+}
+
+/// Save the thrust history of the last run in a replay file.
+#[derive(Event)]
+pub struct ExportReplay;
+
+/// Load the replay file of the current level.
+#[derive(Event)]
+pub struct ImportReplay;
+
+/// Save or load the TAS script of the current level.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum Script {
+    /// Write the TAS inputs.
+    Save,
+    /// Race the script.
+    LoadGhost,
+    /// Edit the script in TAS mode.
+    LoadInputs,
+}
+
+/// The ship got destroyed, the level is restarting.
+#[derive(Event)]
+pub struct ShipDestroyed {
+    pub pos: Vec2,
+    pub cause: Destruction,
+}
+
+/// Why the ship got destroyed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destruction {
+    Crash(crate::physics::Crash),
+    /// The landing rules of the level were broken.
+    HardLanding,
+}
+
 #[derive(Event, Default)]
 pub enum Thruster {
     #[default]
@@ -24,6 +78,14 @@ struct StoppingThruster;
 pub struct Plug;
 impl Plugin for Plug {
     fn build(&self, app: &mut App) {
-        app.add_event::<NewHighscore>().add_event::<Thruster>();
+        app.add_event::<NewHighscore>()
+            .add_event::<NewFuelRecord>()
+            .add_event::<NewGhost>()
+            .add_event::<NewTasRecord>()
+            .add_event::<ExportReplay>()
+            .add_event::<ImportReplay>()
+            .add_event::<Script>()
+            .add_event::<Thruster>()
+            .add_event::<ShipDestroyed>();
     }
 }
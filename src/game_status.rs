@@ -8,13 +8,14 @@
 use bevy::prelude::*;
 
 use crate::app_status::AppStatus;
-use crate::entities::goal::Goal;
 use crate::entities::*;
-use crate::events::NewHighscore;
+use crate::events::{
+    Destruction, NewFuelRecord, NewGhost, NewHighscore, NewTasRecord, ShipDestroyed,
+};
 use crate::level;
+use crate::physics;
 use crate::resources;
-use crate::resources::GameResources;
-use bevy::sprite::collide_aabb::{collide, Collision};
+use crate::resources::{GameResources, GhostKind};
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameStatus {
@@ -25,8 +26,13 @@ pub enum GameStatus {
     Flying,
 }
 
+/// The level is played normally or in TAS mode.
+pub fn in_playing() -> impl Condition<()> {
+    in_state(AppStatus::Playing).or_else(in_state(AppStatus::Tas))
+}
+
 fn in_playing_state(gs: GameStatus) -> impl Condition<()> {
-    in_state(AppStatus::Playing).and_then(in_state(gs))
+    in_playing().and_then(in_state(gs))
 }
 
 pub struct Plug;
@@ -36,22 +42,40 @@ impl Plugin for Plug {
             .init_resource::<resources::GameResources>()
             .insert_resource(crate::level_loader::load())
             .init_resource::<launch_pad::PadMaterials>()
+            .init_resource::<checkpoint::CheckpointMaterials>()
+            .add_plugins(MaterialPlugin::<black_hole::BlackHoleMaterial>::default())
             .add_systems(
                 OnEnter(GameStatus::Spawning),
                 (update_ghost, level::despawn, level::setup, setup_idling).chain(),
             )
-            .add_systems(Update, handle_input.run_if(in_state(AppStatus::Playing)))
+            .add_systems(Update, handle_input.run_if(in_playing()))
             .add_systems(
                 Update,
-                (goal::animate, velocity_gizmo::update_gizmo)
+                (
+                    goal::animate,
+                    velocity_gizmo::update_gizmo,
+                    cargo::update,
+                    wall::update_movers,
+                    crate::ui::fuel::update,
+                    checkpoint::update,
+                )
                     .run_if(in_playing_state(GameStatus::Flying)),
             )
+            .add_systems(Update, (explosion::spawn, explosion::animate))
             // Configure how frequently our gameplay systems are run
-            .insert_resource(Time::<Fixed>::from_hz(60.0))
+            .insert_resource(Time::<Fixed>::from_hz(physics::TICK_RATE as f64))
             .add_systems(
                 FixedUpdate,
-                ((move_ship, check_goal).after(handle_input))
-                    .run_if(in_playing_state(GameStatus::Flying)),
+                ((
+                    crate::tas::apply_step,
+                    move_ship,
+                    check_checkpoints,
+                    check_goal,
+                )
+                    .chain()
+                    .after(handle_input))
+                .run_if(in_playing_state(GameStatus::Flying))
+                .run_if(crate::tas::can_step),
             );
     }
 }
@@ -62,29 +86,93 @@ pub fn setup_idling(mut next_game_status: ResMut<NextState<GameStatus>>) {
     next_game_status.set(GameStatus::Idling)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn check_goal(
     mut state: ResMut<GameResources>,
-    mut query: ParamSet<(
-        Query<&Transform, With<ship::Ship>>,
-        Query<&mut Transform, With<goal::Goal>>,
-    )>,
-    mut text: Query<&mut Text>,
+    mut goal_query: Query<&mut Transform, With<goal::Goal>>,
+    mut text: Query<&mut Text, Without<crate::ui::timeline::TimelineText>>,
     mut next_app_status: ResMut<NextState<AppStatus>>,
     mut highscore_event: EventWriter<NewHighscore>,
+    mut fuel_event: EventWriter<NewFuelRecord>,
+    mut tas_event: EventWriter<NewTasRecord>,
     pad_target_material: Res<launch_pad::PadMaterials>,
     mut pad_query: Query<&mut Handle<StandardMaterial>, With<launch_pad::Pad>>,
+    mut next_game_status: ResMut<NextState<GameStatus>>,
+    mut destroyed_event: EventWriter<ShipDestroyed>,
 ) {
+    // The level respawns at the next state transition, after the remaining ticks of the frame.
+    if state.destroyed {
+        return;
+    }
     let mut text = text.single_mut();
     let text = &mut text.sections[0].value;
 
-    let ship_pos = query.p0().single().translation.truncate();
+    let state = &mut *state;
+    let outcome = state.objectives.check(
+        &mut state.progress,
+        &state.world,
+        &state.ship,
+        &state.contacts,
+    );
+    match outcome {
+        level::Outcome::Racing => {
+            *text = format!("{}: Reach goal with wasd", state.elapsed());
+        }
+        level::Outcome::GoalReached => {
+            info!("Reached goal! {}", state.progress.score);
+            let mut goal = goal_query.single_mut();
+            if let Some(next_goal) = state.objectives.goals.get(state.progress.score) {
+                goal.translation = next_goal.extend(0.0);
+            } else {
+                // highlight the launch pad
+                let mut pad = pad_query.single_mut();
+                *pad = pad_target_material.active.clone();
 
-    if state.score >= state.goals.len() {
-        *text = format!("{}: Land on the green launch pad", state.elapsed());
-        // Check if back on the landing pad
-        let pad = state.launch_pad;
-        if collide(ship_pos.extend(0.0), ship::Ship::size(), pad.0, pad.1).is_some() {
+                goal.translation = level::OFFSCREEN.extend(0.0);
+            }
+        }
+        level::Outcome::MissingCrates(missing_crates) => {
+            *text = format!("{}: Pick up {} crate(s)", state.elapsed(), missing_crates);
+        }
+        level::Outcome::Resting if state.objectives.landing.rest_frames > 0 => {
+            *text = format!(
+                "{}: Rest on the launch pad {}/{}",
+                state.elapsed(),
+                state.progress.rest_frames,
+                state.objectives.landing.rest_frames
+            );
+        }
+        level::Outcome::Landing | level::Outcome::Resting => {
+            *text = format!("{}: Land on the green launch pad", state.elapsed());
+        }
+        level::Outcome::HardLanding(reason) => {
+            info!("Landing failed: {}", reason);
+            state.destroyed = true;
+            state.failed_attempts += 1;
+            state.landing_failure = Some(reason);
+            destroyed_event.send(ShipDestroyed {
+                pos: state.ship.pos,
+                cause: Destruction::HardLanding,
+            });
+            next_game_status.set(GameStatus::Spawning);
+        }
+        level::Outcome::Completed => {
             let level = state.current_level;
+            if state.used_tas {
+                let score = state.frame_count;
+                info!("Completed a TAS run! score: {}", score);
+                if !matches!(state.tas_records.get(&level), Some(record) if *record <= score) {
+                    state.made_tas_record = true;
+                    tas_event.send(NewTasRecord { level, score });
+                }
+                next_app_status.set(AppStatus::Completed);
+                return;
+            }
+            if state.used_checkpoint {
+                info!("Completed a practice run");
+                next_app_status.set(AppStatus::Completed);
+                return;
+            }
             let highscore = state.prev_score(level);
             let score = state.frame_count;
             info!("Completed! score: {}, prev: {}", score, highscore);
@@ -94,148 +182,159 @@ pub fn check_goal(
                 }
                 highscore_event.send(NewHighscore { level, score });
             }
+            if let Some(fuel) = state.ship.fuel_left(&state.world.params) {
+                match state.fuel_records.get(&level) {
+                    Some(record) if *record >= fuel => {}
+                    Some(_) => {
+                        state.made_fuel_record = true;
+                        fuel_event.send(NewFuelRecord { level, fuel });
+                    }
+                    None => fuel_event.send(NewFuelRecord { level, fuel }),
+                }
+            }
             next_app_status.set(AppStatus::Completed);
         }
-    } else {
-        *text = format!("{}: Reach goal with wasd", state.elapsed());
-        let mut goal_query = query.p1();
-        let mut goal = goal_query.single_mut();
-        let goal_pos = goal.translation.truncate();
-
-        if Goal::reached(goal_pos, ship_pos) {
-            info!("Reached goal! {}", state.score);
-            state.score += 1;
-            if let Some(next_goal) = state.goals.get(state.score) {
-                goal.translation = next_goal.extend(0.0);
-            } else {
-                // highlight the launch pad
-                let mut pad = pad_query.single_mut();
-                *pad = pad_target_material.active.clone();
+    }
+}
 
-                goal.translation = level::OFFSCREEN.extend(0.0);
-            }
-        }
+/// Save the state when touching a new checkpoint.
+pub fn check_checkpoints(mut state: ResMut<GameResources>) {
+    if state.destroyed {
+        return;
+    }
+    let touched = state.checkpoint.as_ref().map(|checkpoint| checkpoint.idx);
+    let reached = state
+        .checkpoints
+        .iter()
+        .position(|pos| checkpoint::Checkpoint::reached(*pos, state.ship.pos));
+    if let Some(idx) = reached.filter(|idx| Some(*idx) != touched) {
+        info!("Reached checkpoint {}", idx);
+        state.checkpoint = Some(resources::Checkpoint {
+            idx,
+            ship: state.ship,
+            score: state.progress.score,
+            frame_count: state.frame_count,
+            thrust_history: state.thrust_history.clone(),
+        });
     }
 }
 
 pub fn update_ghost(
     mut game_state: ResMut<crate::resources::GameResources>,
     levels: Res<level::Levels>,
-    collider_query: Query<&wall::WallPosition>,
+    difficulty: Res<resources::Difficulty>,
+    pkv: Res<bevy_pkv::PkvStore>,
+    mut ghost_event: EventWriter<NewGhost>,
 ) {
     let level = levels.0.get(game_state.current_level).unwrap();
-    if let Some(prev_ghost) = &game_state.ghost {
-        info!(
-            "Prev score/frame {}/{}  current {}/{}",
-            prev_ghost.score, prev_ghost.frame_count, game_state.score, game_state.frame_count
-        );
-        if prev_ghost.score > game_state.score
-            || (prev_ghost.score == game_state.score
-                && prev_ghost.frame_count <= game_state.frame_count)
-        {
-            info!("Ignored ghost");
-            return;
-        }
+    let initial = level::initial_ship_center(level, &level::Screen::default());
+    let positions = compute_ghost(&game_state.world, initial, &game_state.thrust_history);
+    // The previous attempt is raced even when it failed, the TAS runs are shown by the timeline.
+    if !game_state.thrust_history.is_empty() && !game_state.used_tas {
+        let last = resources::Ghost {
+            kind: GhostKind::Last,
+            score: game_state.progress.score,
+            frame_count: game_state.frame_count,
+            thrust_history: game_state.thrust_history.clone(),
+            positions: positions.clone(),
+        };
+        game_state.ghosts.set(last);
+    }
+    if game_state.destroyed {
+        info!("Ignored failed attempt");
+        return;
+    }
+    if game_state.used_checkpoint || game_state.used_tas {
+        info!("Ignored practice or TAS run");
+        return;
     }
-    let screen = level::Screen::default();
-    let ghost = compute_ghost(
-        level::initial_ship_pos(&level, &screen),
+    // The run is judged by the level rules, an empty or unfinished run is not a ghost.
+    let run = level::play(
+        &game_state.world,
+        &game_state.objectives,
+        physics::ShipState::new(initial),
         &game_state.thrust_history,
-        &collider_query,
     );
+    if run.end != Some(level::End::Completed) {
+        info!("Ignored unfinished run");
+        return;
+    }
+    // Compare with the saved ghost when it is not raced yet.
+    resources::save::load_best_ghost(&mut game_state, level, &difficulty, &pkv);
+    if !game_state.ghosts.is_new_best(&run) {
+        info!("Ignored ghost");
+        return;
+    }
     info!("Saving new ghost!");
-    game_state.ghost = Some(resources::Ghost {
-        score: game_state.score,
-        frame_count: game_state.frame_count,
-        positions: ghost,
+    let best = resources::Ghost {
+        kind: GhostKind::Best,
+        score: run.progress.score,
+        frame_count: run.frames,
+        thrust_history: game_state.thrust_history.clone(),
+        positions,
+    };
+    game_state.ghosts.set(best);
+    ghost_event.send(NewGhost {
+        level: game_state.current_level,
     });
 }
 
-fn compute_ghost(
+pub fn compute_ghost(
+    world: &physics::World,
     initial_pos: Vec2,
-    thrust_history: &Vec<Vec2>,
-    collider_query: &Query<&wall::WallPosition>,
+    thrust_history: &[Vec2],
 ) -> Vec<Vec3> {
-    let mut ghost = Vec::with_capacity(thrust_history.len());
-    let mut velocity = ship::Velocity(Vec2::new(0.0, 0.0));
-    let mut pos = initial_pos.extend(0.0);
-    for thrust in thrust_history {
-        (velocity, pos) = simulate_ship(thrust, &velocity, pos, collider_query);
-        ghost.push(pos);
-    }
-    ghost
-}
-
-fn simulate_ship(
-    current_thrust: &Vec2,
-    velocity: &ship::Velocity,
-    pos: Vec3,
-    collider_query: &Query<&wall::WallPosition>,
-) -> (ship::Velocity, Vec3) {
-    let thrust_power = Vec2::new(0.01, 0.013);
-    let damp = 0.90;
-    let gravity = Vec3::new(0.0, -0.01, 0.0);
-
-    let mut new_velocity = ship::Velocity(damp * (*current_thrust * thrust_power + velocity.0));
-    let mut new_pos: Vec3 = pos + gravity + new_velocity.0.extend(0.0);
-
-    for wall in collider_query {
-        if let Some(collision) = collide(new_pos, ship::Ship::size(), wall.translation, wall.size) {
-            match collision {
-                Collision::Left => {
-                    new_velocity.0.x = 0.;
-                    new_pos.x = wall.left() - ship::SHIP_RADIUS;
-                }
-                Collision::Right => {
-                    new_velocity.0.x = 0.;
-                    new_pos.x = wall.right() + ship::SHIP_RADIUS;
-                }
-                Collision::Top => {
-                    new_velocity.0.y = 0.;
-                    new_pos.y = wall.top() + ship::SHIP_RADIUS;
-                }
-                Collision::Bottom => {
-                    new_velocity.0.y = 0.;
-                    new_pos.y = wall.bottom() - ship::SHIP_RADIUS;
-                }
-                _ => { /* do nothing */ }
-            }
-        }
-    }
-
-    (new_velocity, new_pos)
+    world
+        .simulate(physics::ShipState::new(initial_pos), thrust_history)
+        .into_iter()
+        .map(|ship| ship.pos.extend(0.0))
+        .collect()
 }
 
 pub fn move_ship(
     mut state: ResMut<GameResources>,
     mut ship_query: ParamSet<(
         Query<(&mut Transform, &mut ship::Velocity), With<ship::Ship>>,
-        Query<&mut Transform, With<ship::Ghost>>,
+        Query<(&mut Transform, &ship::Ghost)>,
     )>,
-    collider_query: Query<&wall::WallPosition>,
+    mut next_game_status: ResMut<NextState<GameStatus>>,
+    mut destroyed_event: EventWriter<ShipDestroyed>,
 ) {
-    let mut ship_binding = ship_query.p0();
-    let mut ship = ship_binding.single_mut();
+    if state.destroyed {
+        return;
+    }
     let current_thrust: Vec2 = state.thrust;
+    let result = state.world.step(&state.ship, current_thrust);
+    if let Some(crash) = result.crash {
+        info!("Ship destroyed: {:?}", crash);
+        state.destroyed = true;
+        state.failed_attempts += 1;
+        destroyed_event.send(ShipDestroyed {
+            pos: result.ship.pos,
+            cause: Destruction::Crash(crash),
+        });
+        next_game_status.set(GameStatus::Spawning);
+        return;
+    }
+    state.ship = result.ship;
+    state.contacts = result.contacts;
 
-    let (new_velocity, new_pos) = simulate_ship(
-        &current_thrust,
-        &ship.1,
-        ship.0.translation,
-        &collider_query,
-    );
-
-    *ship.1 = new_velocity;
-    ship.0.translation = new_pos;
+    let mut ship_binding = ship_query.p0();
+    let mut ship = ship_binding.single_mut();
+    ship.0.translation = state.ship.pos.extend(0.0);
+    ship.0.rotation = Quat::from_rotation_z(state.ship.heading);
+    *ship.1 = ship::Velocity(state.ship.velocity);
 
     let current_frame = state.frame_count;
-    if let Some(pos) = state
-        .ghost
-        .as_ref()
-        .and_then(|ghost| ghost.positions.get(current_frame))
-    {
-        let mut query = ship_query.p1();
-        query.single_mut().translation = *pos;
+    for (mut transform, ship::Ghost(kind)) in ship_query.p1().iter_mut() {
+        let Some(ghost) = state.ghosts.get(*kind) else {
+            continue;
+        };
+        if !state.ghosts.is_visible(*kind) {
+            transform.translation = level::OFFSCREEN.extend(0.0);
+        } else if let Some(pos) = ghost.positions.get(current_frame) {
+            transform.translation = *pos;
+        }
     }
 
     state.frame_count += 1;
@@ -263,11 +362,15 @@ const AR_W: ScanCode = ScanCode(39);
 const AU_W: ScanCode = ScanCode(38);
 const AD_W: ScanCode = ScanCode(40);
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_input(
     mut state: ResMut<GameResources>,
     game_status: Res<State<GameStatus>>,
     keyboard_input: Res<Input<ScanCode>>,
     gamepad_button_input: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    stick_settings: Res<resources::StickSettings>,
     mut next_game_status: ResMut<NextState<GameStatus>>,
     mut events: EventWriter<crate::events::Thruster>,
 ) {
@@ -298,6 +401,26 @@ pub fn handle_input(
         }
     }
     state.thrust = Vec2::new(dx, dy);
+
+    // The left stick gives a proportional thrust when the digital inputs are released.
+    if state.thrust == Vec2::ZERO {
+        for gamepad in gamepads.iter() {
+            let axis = |axis_type| {
+                gamepad_axes
+                    .get(GamepadAxis::new(gamepad, axis_type))
+                    .unwrap_or(0.0)
+            };
+            let stick = Vec2::new(
+                axis(GamepadAxisType::LeftStickX),
+                axis(GamepadAxisType::LeftStickY),
+            );
+            let thrust = stick_settings.thrust(stick);
+            if thrust != Vec2::ZERO {
+                state.thrust = thrust;
+                break;
+            }
+        }
+    }
     if prev_thrust != state.thrust {
         if prev_thrust == Vec2::ZERO {
             events.send(crate::events::Thruster::Firing)
@@ -308,7 +431,6 @@ pub fn handle_input(
 
     if state.thrust != default() && game_status.get() == &GameStatus::Idling {
         info!("Lift off!");
-        state.frame_count = 0;
         next_game_status.set(GameStatus::Flying);
     }
 }
@@ -4,8 +4,11 @@
 //! This module defines the level structure and how to render it.
 
 use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
 
 use crate::entities::{launch_pad::PadMaterials, *};
+use crate::physics;
+use crate::resources::GhostKind;
 
 #[derive(Debug)]
 pub struct Rectangle {
@@ -34,6 +37,22 @@ impl Rectangle {
     }
 }
 
+/// A wall that follows a path.
+#[derive(Debug)]
+pub struct Mover {
+    pub rect: Rectangle,
+    pub path: Vec<Vec2>,
+    /// The number of frames to complete the path.
+    pub period: usize,
+    pub looped: bool,
+}
+
+#[derive(Debug)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
 pub struct Screen {
     dim: Vec2,
     center: Vec2,
@@ -61,19 +80,301 @@ impl Screen {
         pos.y *= -1.0;
         pos
     }
-    pub fn default() -> Self {
+
+    /// The level is not scaled uniformly, thus the radius uses the average scale.
+    fn radius(&self, radius: f32) -> f32 {
+        let scale = self.dim / LEVEL_SIZE;
+        radius * (scale.x + scale.y) / 2.0
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
         Screen::new(Vec2::new(8.85, 5.0))
     }
 }
 
 pub const OFFSCREEN: Vec2 = Vec2::new(50.0, 50.0);
 
+#[derive(Debug)]
+pub struct Bumper {
+    pub rect: Rectangle,
+    pub restitution: f32,
+}
+
+/// The conditions to complete the level on the launch pad, the limits are only set by the levels
+/// that declare them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LandingRules {
+    /// The maximum vertical speed when touching the pad.
+    pub max_speed: Option<f32>,
+    /// The maximum horizontal speed when touching the pad.
+    pub max_drift: Option<f32>,
+    /// The number of frames the ship must rest on the pad.
+    pub rest_frames: usize,
+}
+
+impl LandingRules {
+    /// Returns the reason why the landing failed.
+    pub fn check(&self, speed: f32, drift: f32) -> Option<String> {
+        match (self.max_speed, self.max_drift) {
+            (Some(max), _) if speed > max => Some(format!("Too fast! {:.3} > {:.3}", speed, max)),
+            (_, Some(max)) if drift.abs() > max => {
+                Some(format!("Too much drift! {:.3} > {:.3}", drift.abs(), max))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The objectives of a level in screen coordinates, checked after each step of the simulation.
+#[derive(Debug, Clone, Default)]
+pub struct Objectives {
+    pub goals: Vec<Vec2>,
+    /// The center and size of the launch pad.
+    pub pad: (Vec3, Vec2),
+    pub landing: LandingRules,
+}
+
+/// The progress of a run toward the objectives.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    /// The number of goals reached.
+    pub score: usize,
+    /// The number of frames the ship rested on the launch pad.
+    pub rest_frames: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The ship is flying to the next goal.
+    Racing,
+    GoalReached,
+    /// The number of crates to pick up before landing.
+    MissingCrates(usize),
+    /// The ship must land on the launch pad.
+    Landing,
+    /// The ship is on the launch pad, but it did not rest long enough.
+    Resting,
+    Completed,
+    /// The reason why the landing failed.
+    HardLanding(String),
+}
+
+impl Objectives {
+    /// Check the ship after a step of the simulation.
+    pub fn check(
+        &self,
+        progress: &mut Progress,
+        world: &physics::World,
+        ship: &physics::ShipState,
+        contacts: &[physics::Contact],
+    ) -> Outcome {
+        if let Some(goal) = self.goals.get(progress.score) {
+            if goal::Goal::reached(*goal, ship.pos) {
+                progress.score += 1;
+                return Outcome::GoalReached;
+            }
+            return Outcome::Racing;
+        }
+        let missing_crates = world.crates.len() - ship.cargo_count();
+        if missing_crates > 0 {
+            return Outcome::MissingCrates(missing_crates);
+        }
+        // Check if back on the landing pad
+        let (pad_pos, pad_size) = self.pad;
+        if collide(ship.pos.extend(0.0), ship::Ship::size(), pad_pos, pad_size).is_none() {
+            progress.rest_frames = 0;
+            return Outcome::Landing;
+        }
+        // The speed at which the ship touched the pad during this frame.
+        let touchdown = contacts
+            .iter()
+            .filter(|contact| contact.normal == Vec2::Y && self.above_pad(ship))
+            .map(|contact| contact.speed)
+            .reduce(f32::max);
+        if let Some(speed) = touchdown {
+            if let Some(reason) = self.landing.check(speed, ship.velocity.x) {
+                return Outcome::HardLanding(reason);
+            }
+            progress.rest_frames += 1;
+        } else {
+            progress.rest_frames = 0;
+        }
+        // The ship needs to touch the ground, so that the landing rules are checked.
+        if progress.rest_frames < self.landing.rest_frames.max(1) {
+            Outcome::Resting
+        } else {
+            Outcome::Completed
+        }
+    }
+
+    /// True when the ship stands on the launch pad, touching a wall next to it is not a landing.
+    fn above_pad(&self, ship: &physics::ShipState) -> bool {
+        let (pad_pos, pad_size) = self.pad;
+        let bottom = ship.pos.y - ship::SHIP_RADIUS;
+        (ship.pos.x - pad_pos.x).abs() <= pad_size.x / 2.0
+            && (bottom - pad_pos.y).abs() <= pad_size.y / 2.0 + 1e-4
+    }
+}
+
+/// The way a run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum End {
+    Completed,
+    Crashed(physics::Crash),
+    /// The reason why the landing failed.
+    HardLanding(String),
+}
+
+/// The result of a simulated run, see [play].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// The ship after the last simulated frame, including the one that ended the run.
+    pub ship: physics::ShipState,
+    pub progress: Progress,
+    pub frames: usize,
+    pub end: Option<End>,
+}
+
+/// Simulate the inputs like the game does, until the run ends.
+pub fn play(
+    world: &physics::World,
+    objectives: &Objectives,
+    mut ship: physics::ShipState,
+    inputs: &[Vec2],
+) -> Run {
+    let mut progress = Progress::default();
+    let mut end = None;
+    let mut frames = 0;
+    for thrust in inputs {
+        let result = world.step(&ship, *thrust);
+        ship = result.ship;
+        frames += 1;
+        if let Some(crash) = result.crash {
+            end = Some(End::Crashed(crash));
+            break;
+        }
+        match objectives.check(&mut progress, world, &ship, &result.contacts) {
+            Outcome::Completed => end = Some(End::Completed),
+            Outcome::HardLanding(reason) => end = Some(End::HardLanding(reason)),
+            _ => continue,
+        }
+        break;
+    }
+    Run {
+        ship,
+        progress,
+        frames,
+        end,
+    }
+}
+
 #[derive(Resource, Debug)]
 pub struct Level {
     pub name: String,
     pub walls: Vec<Rectangle>,
+    pub bumpers: Vec<Bumper>,
+    pub traps: Vec<Rectangle>,
+    pub movers: Vec<Mover>,
     pub pad: Rectangle,
+    pub landing: LandingRules,
     pub goals: Vec<Vec2>,
+    pub checkpoints: Vec<Vec2>,
+    pub crates: Vec<Vec2>,
+    pub black_holes: Vec<Vec2>,
+    pub planets: Vec<Circle>,
+    pub physics: physics::PhysicsParams,
+}
+
+impl Level {
+    /// The hash of the level data, to check that a replay belongs to the level.
+    pub fn hash(&self) -> u64 {
+        crate::replay::hash(&self.encode())
+    }
+
+    /// The canonical encoding of the data that changes the simulation, the name is left out.
+    /// Each field is written in a fixed order with the bits of the floats, so that the encoding
+    /// does not depend on the Rust types. The new fields must be written at the end.
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Encoder::default();
+        let rect = |data: &mut Encoder, rect: &Rectangle| {
+            data.vec2(rect.top_left);
+            data.vec2(rect.size);
+        };
+        data.list(&self.walls, rect);
+        data.list(&self.bumpers, |data, bumper| {
+            rect(data, &bumper.rect);
+            data.f32(bumper.restitution);
+        });
+        data.list(&self.traps, rect);
+        data.list(&self.movers, |data, mover| {
+            rect(data, &mover.rect);
+            data.list(&mover.path, |data, pos| data.vec2(*pos));
+            data.usize(mover.period);
+            data.bool(mover.looped);
+        });
+        rect(&mut data, &self.pad);
+        data.option(self.landing.max_speed, Encoder::f32);
+        data.option(self.landing.max_drift, Encoder::f32);
+        data.usize(self.landing.rest_frames);
+        for positions in [
+            &self.goals,
+            &self.checkpoints,
+            &self.crates,
+            &self.black_holes,
+        ] {
+            data.list(positions, |data, pos| data.vec2(*pos));
+        }
+        data.list(&self.planets, |data, planet| {
+            data.vec2(planet.center);
+            data.f32(planet.radius);
+        });
+        let physics = &self.physics;
+        data.vec2(physics.gravity);
+        data.f32(physics.damping);
+        data.vec2(physics.thrust_power);
+        data.option(physics.crash_speed, Encoder::f32);
+        data.f32(physics.planet_gravity);
+        data.usize(match physics.control {
+            physics::Control::Direct => 0,
+            physics::Control::Lander => 1,
+        });
+        data.option(physics.fuel, Encoder::usize);
+        data.0
+    }
+}
+
+/// The little-endian writer of [Level::encode].
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn usize(&mut self, value: usize) {
+        self.0.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+    fn bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+    fn option<T>(&mut self, value: Option<T>, write: fn(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+    fn list<T>(&mut self, values: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.usize(values.len());
+        for value in values {
+            write(self, value);
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -112,7 +413,16 @@ pub fn _simple() -> Level {
     Level {
         name: "simple".into(),
         walls,
+        bumpers: vec![],
+        traps: vec![],
+        movers: vec![],
         goals,
+        checkpoints: vec![],
+        crates: vec![],
+        black_holes: vec![],
+        planets: vec![],
+        physics: default(),
+        landing: default(),
         pad: Rectangle {
             // center platform
             top_left: [37., 54.5].into(),
@@ -121,21 +431,84 @@ pub fn _simple() -> Level {
     }
 }
 
+impl Level {
+    pub fn objectives(&self, screen: &Screen) -> Objectives {
+        let (pad_pos, pad_size) = screen.center_pos(&self.pad);
+        Objectives {
+            goals: self
+                .goals
+                .iter()
+                .map(|goal| screen.goal_pos(*goal))
+                .collect(),
+            pad: (pad_pos.extend(0.0), pad_size),
+            landing: self.landing,
+        }
+    }
+
+    /// The physics world of the level, in screen coordinates.
+    pub fn world(&self, screen: &Screen) -> physics::World {
+        let walls = self.walls.iter().map(|wall| {
+            let (pos, sz) = screen.center_pos(wall);
+            physics::Wall::new(pos, sz)
+        });
+        let bumpers = self.bumpers.iter().map(|bumper| {
+            let (pos, sz) = screen.center_pos(&bumper.rect);
+            physics::Wall::bumper(pos, sz, bumper.restitution)
+        });
+        let traps = self.traps.iter().map(|trap| {
+            let (pos, sz) = screen.center_pos(trap);
+            physics::Wall::trap(pos, sz)
+        });
+        let walls = walls.chain(bumpers).chain(traps).collect();
+        let crates = self.crates.iter().map(|pos| screen.goal_pos(*pos));
+        let black_holes = self.black_holes.iter().map(|pos| screen.goal_pos(*pos));
+        let planets = self.planets.iter().map(|planet| {
+            physics::Planet::new(screen.goal_pos(planet.center), screen.radius(planet.radius))
+        });
+        let movers = self.movers.iter().map(|mover| {
+            let (pos, sz) = screen.center_pos(&mover.rect);
+            physics::Mover {
+                wall: physics::Wall::new(pos, sz),
+                path: mover.path.iter().map(|pos| screen.goal_pos(*pos)).collect(),
+                period: mover.period,
+                looped: mover.looped,
+            }
+        });
+        physics::World {
+            crates: crates.take(physics::MAX_CRATES).collect(),
+            black_holes: black_holes.collect(),
+            planets: planets.collect(),
+            movers: movers.collect(),
+            ..physics::World::new(walls, self.physics)
+        }
+    }
+}
+
+/// The position of the bottom of the ship when it rests on the launch pad.
 pub fn initial_ship_pos(level: &Level, screen: &Screen) -> Vec2 {
     let (pad_pos, pad_size) = screen.center_pos(&level.pad);
     Vec2::new(pad_pos.x, pad_pos.y - pad_size.y / 2.0)
 }
 
+/// The center of the ship resting on the launch pad, where the simulation starts.
+pub fn initial_ship_center(level: &Level, screen: &Screen) -> Vec2 {
+    ship::Ship::center(initial_ship_pos(level, screen))
+}
+
 #[derive(Component)]
 pub struct LevelComponent;
 
+#[allow(clippy::too_many_arguments)]
 pub fn setup(
     mut commands: Commands,
     mut game_state: ResMut<crate::resources::GameResources>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut black_hole_materials: ResMut<Assets<black_hole::BlackHoleMaterial>>,
     pad_materials: Res<PadMaterials>,
+    checkpoint_materials: Res<checkpoint::CheckpointMaterials>,
     levels: Res<Levels>,
+    difficulty: Res<crate::resources::Difficulty>,
 ) {
     info!("Level setup called!");
     let screen = Screen::default();
@@ -144,36 +517,112 @@ pub fn setup(
 
     // walls
     let wmat = wall::WallBundle::material(&mut materials);
-    for wall in level.walls.iter() {
-        let (pos, sz) = screen.center_pos(wall);
-
+    let bmat = wall::WallBundle::bumper_material(&mut materials);
+    let tmat = wall::WallBundle::trap_material(&mut materials);
+    let mut world = level.world(&screen);
+    difficulty.apply(&mut world.params);
+    for wall in world.walls.iter() {
+        let material = match wall.kind {
+            physics::WallKind::Solid => &wmat,
+            physics::WallKind::Bumper { .. } => &bmat,
+            physics::WallKind::Trap => &tmat,
+        };
         commands.spawn((
-            wall::WallBundle::new(&mut meshes, &wmat, pos, sz),
+            wall::WallBundle::new(&mut meshes, material, wall.center, wall.size),
             wall::Wall,
             LevelComponent,
         ));
     }
+    for (idx, mover) in world.movers.iter().enumerate() {
+        commands.spawn((
+            wall::WallBundle::new(&mut meshes, &wmat, mover.wall.center, mover.wall.size),
+            wall::Mover(idx),
+            LevelComponent,
+        ));
+    }
+
+    // spawn the crates, they are attached to the ship by cargo::update
+    for (idx, pos) in world.crates.iter().enumerate() {
+        commands.spawn((
+            cargo::CargoBundle::new(&mut meshes, &mut materials, *pos),
+            cargo::Cargo(idx),
+            LevelComponent,
+        ));
+    }
+    for pos in world.black_holes.iter() {
+        commands.spawn((
+            black_hole::new(&mut meshes, &mut black_hole_materials, *pos),
+            LevelComponent,
+        ));
+    }
+    for planet in world.planets.iter() {
+        commands.spawn((
+            planet::PlanetBundle::new(&mut meshes, &mut materials, planet),
+            LevelComponent,
+        ));
+    }
+    let control = world.params.control;
+    if let Some(fuel) = world.params.fuel {
+        crate::ui::fuel::spawn(&mut commands, fuel);
+    }
+    game_state.world = world;
+
+    // Resume from the last checkpoint when requested, otherwise start a new run
+    let resume = if std::mem::take(&mut game_state.respawn_at_checkpoint) {
+        game_state.checkpoint.clone()
+    } else {
+        None
+    };
+    game_state.used_checkpoint = resume.is_some();
+    game_state.used_tas = false;
 
     // Reset controller
     game_state.thrust = default();
-    game_state.score = 0;
+    game_state.progress = Progress {
+        score: resume.as_ref().map_or(0, |checkpoint| checkpoint.score),
+        rest_frames: 0,
+    };
+    game_state.frame_count = resume
+        .as_ref()
+        .map_or(0, |checkpoint| checkpoint.frame_count);
+    game_state.destroyed = false;
     game_state.made_highscore = false;
-    game_state.thrust_history.clear();
+    game_state.made_fuel_record = false;
+    game_state.made_tas_record = false;
+    game_state.thrust_history = resume
+        .as_ref()
+        .map_or(vec![], |checkpoint| checkpoint.thrust_history.clone());
 
     // register goals
-    game_state.goals.clear();
-    for goal in level.goals.iter() {
-        game_state.goals.push(screen.goal_pos(*goal));
+    game_state.objectives = level.objectives(&screen);
+
+    // register checkpoints
+    game_state.checkpoints.clear();
+    for (idx, pos) in level.checkpoints.iter().enumerate() {
+        let pos = screen.goal_pos(*pos);
+        game_state.checkpoints.push(pos);
+        commands.spawn((
+            checkpoint::CheckpointBundle::new(&mut meshes, &checkpoint_materials.idle, pos),
+            checkpoint::Checkpoint(idx),
+            LevelComponent,
+        ));
     }
 
     let (pad_pos, pad_size) = screen.center_pos(&level.pad);
-    let pad_bundle =
-        launch_pad::PadBundle::new(&mut meshes, &pad_materials.idle, pad_pos, pad_size);
-    game_state.launch_pad = (pad_pos.extend(0.0), pad_size);
+    let next_goal = game_state
+        .objectives
+        .goals
+        .get(game_state.progress.score)
+        .copied();
+    let pad_material = match next_goal {
+        Some(_) => &pad_materials.idle,
+        None => &pad_materials.active,
+    };
+    let pad_bundle = launch_pad::PadBundle::new(&mut meshes, pad_material, pad_pos, pad_size);
     commands.spawn((pad_bundle, launch_pad::Pad, LevelComponent));
 
-    // spawn first goal
-    let goal_pos = screen.goal_pos(level.goals[0]);
+    // spawn the next goal
+    let goal_pos = next_goal.unwrap_or(OFFSCREEN);
     info!("goal: {}", goal_pos);
     commands.spawn((
         goal::GoalBundle::new(&mut meshes, &mut materials, goal_pos),
@@ -181,31 +630,49 @@ pub fn setup(
         LevelComponent,
     ));
 
-    // spawn the ship on the pad
-    let ship_pos = initial_ship_pos(&level, &screen);
+    // spawn the ship on the pad, or at the checkpoint
+    game_state.ship = match &resume {
+        Some(checkpoint) => checkpoint.ship,
+        None => physics::ShipState::new(initial_ship_center(level, &screen)),
+    };
+    let mut ship_bundle = ship::ShipBundle::new(
+        &mut meshes,
+        &mut materials,
+        initial_ship_pos(level, &screen),
+        Color::rgb_linear(5.0, 5.0, 5.0),
+    );
+    // The simulation holds the center of the ship, which is the checkpoint when resuming.
+    ship_bundle.pbr.transform.translation = game_state.ship.pos.extend(0.0);
+    ship_bundle.vel = ship::Velocity(game_state.ship.velocity);
+    ship_bundle.pbr.transform.rotation = Quat::from_rotation_z(game_state.ship.heading);
     commands
-        .spawn((
-            ship::ShipBundle::new(&mut meshes, &mut materials, ship_pos, 0.0),
-            ship::Ship,
-            LevelComponent,
-        ))
+        .spawn((ship_bundle, ship::Ship, LevelComponent))
         .with_children(|parent| {
             parent.spawn((
                 velocity_gizmo::new(&mut meshes, &mut materials),
                 velocity_gizmo::VelocityGizmo,
             ));
+            if control == physics::Control::Lander {
+                parent.spawn(ship::nose(&mut meshes, &mut materials));
+            }
         });
 
-    commands.spawn((
-        ship::ShipBundle::new(&mut meshes, &mut materials, OFFSCREEN, 4.5),
-        ship::Ghost,
-        LevelComponent,
-    ));
+    for kind in GhostKind::ALL {
+        commands.spawn((
+            ship::ShipBundle::new(&mut meshes, &mut materials, OFFSCREEN, kind.color()),
+            ship::Ghost(kind),
+            LevelComponent,
+        ));
+    }
 
-    // example instructions
+    // example instructions, or the reason of the last failed landing
+    let title = match game_state.landing_failure.take() {
+        Some(reason) => format!("{}: {}", level.name, reason),
+        None => level.name.clone(),
+    };
     commands.spawn((
         TextBundle::from_section(
-            &level.name,
+            title,
             TextStyle {
                 font_size: 20.0,
                 color: Color::WHITE,
@@ -224,3 +691,67 @@ pub fn despawn(mut commands: Commands, entities: Query<Entity, With<LevelCompone
     }
     info!("Despawned level data {}", count);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_touchdown_on_the_pad_is_a_landing() {
+        let mut objectives = Objectives {
+            pad: (Vec3::ZERO, Vec2::new(0.5, 0.04)),
+            ..Default::default()
+        };
+        let world = physics::World::default();
+        let touchdown = [physics::Contact {
+            collider: physics::Collider::Wall(0),
+            time: 0.0,
+            normal: Vec2::Y,
+            speed: 0.1,
+        }];
+        let check = |objectives: &Objectives, x: f32| {
+            let ship = physics::ShipState::new(Vec2::new(x, -0.02 + ship::SHIP_RADIUS));
+            let mut progress = Progress::default();
+            objectives.check(&mut progress, &world, &ship, &touchdown)
+        };
+        // Without a limit declared by the level, any touchdown completes it.
+        assert_eq!(check(&objectives, 0.0), Outcome::Completed);
+        objectives.landing.max_speed = Some(0.05);
+        assert!(matches!(check(&objectives, 0.0), Outcome::HardLanding(_)));
+        // The ship overlaps the pad, but it stands on the wall next to it.
+        assert_eq!(check(&objectives, 0.28), Outcome::Resting);
+    }
+
+    #[test]
+    fn the_hash_only_depends_on_the_level_data() {
+        let mut level = crate::level_loader::load().0.remove(0);
+        let hash = level.hash();
+        assert_eq!(hash, 13_639_696_903_971_049_469);
+        level.name = "renamed".into();
+        assert_eq!(level.hash(), hash);
+        level.walls[0].size.x += 0.01;
+        assert_ne!(level.hash(), hash);
+    }
+
+    #[test]
+    fn the_ship_spawns_at_the_baseline_position() {
+        let screen = Screen::default();
+        let levels = crate::level_loader::load().0;
+        for level in &levels {
+            // The ship bundle is placed on top of the pad, raised by its radius.
+            let (pad_pos, pad_size) = screen.center_pos(&level.pad);
+            let pad_top = Vec2::new(pad_pos.x, pad_pos.y - pad_size.y / 2.0);
+            assert_eq!(initial_ship_pos(level, &screen), pad_top);
+            assert_eq!(
+                initial_ship_center(level, &screen),
+                Vec2::new(pad_top.x, pad_top.y + ship::SHIP_RADIUS)
+            );
+        }
+        // The translation of the ship in the first level, before the physics module.
+        let center = initial_ship_center(&levels[0], &screen);
+        assert_eq!(
+            (center.x.to_bits(), center.y.to_bits()),
+            (0x3440_0000, 0xc004_cccd)
+        );
+    }
+}
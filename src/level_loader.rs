@@ -6,10 +6,20 @@
 //! Note that [usvg](https://docs.rs/usvg) is not usable because of https://github.com/RazrFalcon/resvg/issues/588.
 //! Thus this module uses a regular xml library to manually load the data and normalize the translation.
 
+use bevy::log::{debug, info};
 use bevy::math::Vec2;
 use roxmltree::{Document, Node};
 
-use crate::level::{Level, Levels, Rectangle};
+use std::collections::HashMap;
+
+use crate::level::{Bumper, Circle, LandingRules, Level, Levels, Mover, Rectangle};
+use crate::physics::{Control, PhysicsParams};
+
+/// The restitution of the bumpers without a `restitution` attribute.
+const BUMPER_RESTITUTION: f32 = 0.8;
+
+/// The period of the movers without a `period` attribute, in frames.
+const MOVER_PERIOD: usize = 240;
 
 fn load_rectangle(node: Node, offset: Vec2) -> Option<Rectangle> {
     let parse_attr = |name| get_attr(&node, name)?.parse().ok();
@@ -18,47 +28,250 @@ fn load_rectangle(node: Node, offset: Vec2) -> Option<Rectangle> {
     Some(Rectangle::new(top_left, size))
 }
 
+fn load_bumper(node: Node, offset: Vec2) -> Option<Bumper> {
+    let restitution = match get_attr(&node, "restitution") {
+        Some(value) => value.trim().parse().ok()?,
+        None => BUMPER_RESTITUTION,
+    };
+    Some(Bumper {
+        rect: load_rectangle(node, offset)?,
+        restitution,
+    })
+}
+
+/// Read a `<circle>`, or an `<ellipse>` using its average radius.
+fn load_circle(node: Node, offset: Vec2) -> Option<Circle> {
+    let parse_attr = |name| get_attr(&node, name)?.parse::<f32>().ok();
+    let center = Vec2::new(parse_attr("cx")?, parse_attr("cy")?) - offset;
+    let radius = match node.tag_name().name() {
+        "circle" => parse_attr("r")?,
+        "ellipse" => (parse_attr("rx")? + parse_attr("ry")?) / 2.0,
+        _ => return None,
+    };
+    Some(Circle { center, radius })
+}
+
+/// Read the points of a polyline path, e.g. `m 10,20 30,0 0,15 z`.
+/// Returns the points relative to the offset, and true when the path is closed.
+fn load_path(node: Node, offset: Vec2) -> Option<(Vec<Vec2>, bool)> {
+    let data = get_attr(&node, "d")?;
+    // Separate the commands from the coordinates.
+    let data: String = data
+        .chars()
+        .flat_map(|c| {
+            if "MmLlHhVvZzCcSsQqTtAa".contains(c) {
+                vec![' ', c, ' ']
+            } else {
+                vec![c]
+            }
+        })
+        .collect();
+    let mut tokens = data
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty());
+    let mut points = vec![];
+    let mut closed = false;
+    let mut command = 'M';
+    let mut cursor = Vec2::ZERO;
+    while let Some(token) = tokens.next() {
+        if let Some(c) = token.chars().next().filter(char::is_ascii_alphabetic) {
+            command = c;
+            closed |= c.eq_ignore_ascii_case(&'z');
+            continue;
+        }
+        let value: f32 = token.parse().ok()?;
+        let base = if command.is_ascii_lowercase() {
+            cursor
+        } else {
+            Vec2::ZERO
+        };
+        cursor = match command.to_ascii_uppercase() {
+            'M' | 'L' => base + Vec2::new(value, tokens.next()?.parse().ok()?),
+            'H' => Vec2::new(base.x + value, cursor.y),
+            'V' => Vec2::new(cursor.x, base.y + value),
+            // Curves are not supported.
+            _ => return None,
+        };
+        points.push(cursor - offset);
+    }
+    Some((points, closed))
+}
+
+fn load_mover(node: Node, offset: Vec2, path: Option<(Vec<Vec2>, bool)>) -> Option<Mover> {
+    let period = match get_attr(&node, "period") {
+        Some(value) => value.trim().parse().ok()?,
+        None => MOVER_PERIOD,
+    };
+    let (path, looped) = path?;
+    Some(Mover {
+        rect: load_rectangle(node, offset)?,
+        path,
+        period,
+        looped,
+    })
+}
+
 fn load_pos(node: Node, offset: Vec2) -> Option<Vec2> {
     let rect = load_rectangle(node, offset)?;
     Some(rect.top_left + rect.size / 2.0)
 }
 
-fn load_level(node: &Node) -> Option<Level> {
+fn parse_vec2(value: &str) -> Option<Vec2> {
+    let mut values = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().ok());
+    let vec = Vec2::new(values.next()??, values.next()??);
+    values.next().is_none().then_some(vec)
+}
+
+/// Read an optional attribute, reporting its name when the value is invalid.
+fn parse_attr<T>(
+    node: &Node,
+    name: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<Option<T>, String> {
+    match get_attr(node, name) {
+        Some(value) => parse(value.trim())
+            .map(Some)
+            .ok_or_else(|| format!("invalid {} '{}'", name, value)),
+        None => Ok(None),
+    }
+}
+
+/// Read the optional physics attributes of the level group, for example:
+/// `gravity="0 -0.005" damping="0.95" thrust-power="0.01 0.013" planet-gravity="0.001" control="lander" fuel="600"`
+fn load_physics(node: &Node) -> Result<PhysicsParams, String> {
+    let mut physics = PhysicsParams::default();
+    if let Some(value) = parse_attr(node, "gravity", parse_vec2)? {
+        physics.gravity = value;
+    }
+    if let Some(value) = parse_attr(node, "damping", |v| v.parse().ok())? {
+        physics.damping = value;
+    }
+    if let Some(value) = parse_attr(node, "thrust-power", parse_vec2)? {
+        physics.thrust_power = value;
+    }
+    if let Some(value) = parse_attr(node, "planet-gravity", |v| v.parse().ok())? {
+        physics.planet_gravity = value;
+    }
+    if let Some(value) = parse_attr(node, "fuel", |v| v.parse().ok())? {
+        physics.fuel = Some(value);
+    }
+    let control = |value: &str| match value {
+        "direct" => Some(Control::Direct),
+        "lander" => Some(Control::Lander),
+        _ => None,
+    };
+    if let Some(value) = parse_attr(node, "control", control)? {
+        physics.control = value;
+    }
+    Ok(physics)
+}
+
+/// Read the optional landing attributes of the level group, for example:
+/// `landing-speed="0.05" landing-drift="0.02" landing-rest="30"`
+/// The limits are not checked when they are not set, or set to "none".
+fn load_landing(node: &Node) -> Result<LandingRules, String> {
+    let parse_limit = |value: &str| match value {
+        "none" => Some(None),
+        value => value.parse().ok().map(Some),
+    };
+    let mut landing = LandingRules::default();
+    if let Some(value) = parse_attr(node, "landing-speed", parse_limit)? {
+        landing.max_speed = value;
+    }
+    if let Some(value) = parse_attr(node, "landing-drift", parse_limit)? {
+        landing.max_drift = value;
+    }
+    if let Some(value) = parse_attr(node, "landing-rest", |v| v.parse().ok())? {
+        landing.rest_frames = value;
+    }
+    Ok(landing)
+}
+
+/// Load a level, or explain what is invalid.
+fn load_level(node: &Node) -> Result<Level, String> {
     let screen = node
         .children()
         .find(|node| get_attr(node, "label") == Some("Screen"))
-        .and_then(|node| load_rectangle(node, Vec2::new(0., 0.)))?;
+        .and_then(|node| load_rectangle(node, Vec2::new(0., 0.)))
+        .ok_or("missing Screen")?;
     let offset = screen.top_left;
-    println!("Got screen: {:?}", screen);
+    debug!("Got screen: {:?}", screen);
+    let physics = load_physics(node)?;
+    let landing = load_landing(node)?;
     let mut walls = vec![];
+    let mut bumpers = vec![];
+    let mut traps = vec![];
     let mut goals = vec![];
+    let mut checkpoints = vec![];
+    let mut crates = vec![];
+    let mut black_holes = vec![];
+    let mut planets = vec![];
+    let mut movers = vec![];
+    let mut paths = HashMap::new();
     let mut name = None;
     let mut pad = None;
 
     for node in node.children().filter(|node| node.is_element()) {
-        let label = get_attr(&node, "label")?;
+        let label = get_attr(&node, "label").ok_or_else(|| format!("unlabeled {:?}", node))?;
+        let invalid = || format!("invalid {}", label);
         if label.starts_with("wall-") {
-            walls.push(load_rectangle(node, offset)?);
+            walls.push(load_rectangle(node, offset).ok_or_else(invalid)?);
+        } else if label.starts_with("bumper-") {
+            bumpers.push(load_bumper(node, offset).ok_or_else(invalid)?);
+        } else if label.starts_with("trap-") {
+            traps.push(load_rectangle(node, offset).ok_or_else(invalid)?);
         } else if label == "launch-pad" {
-            pad = load_rectangle(node, offset);
+            pad = Some(load_rectangle(node, offset).ok_or_else(invalid)?);
         } else if let Some(("goal", pos)) = split_pos(label) {
-            goals.push((pos, load_pos(node, offset)?));
+            goals.push((pos, load_pos(node, offset).ok_or_else(invalid)?));
+        } else if let Some(("checkpoint", pos)) = split_pos(label) {
+            checkpoints.push((pos, load_pos(node, offset).ok_or_else(invalid)?));
+        } else if let Some(("crate", pos)) = split_pos(label) {
+            crates.push((pos, load_pos(node, offset).ok_or_else(invalid)?));
+        } else if let Some(("blackhole", pos)) = split_pos(label) {
+            black_holes.push((pos, load_pos(node, offset).ok_or_else(invalid)?));
+        } else if let Some(("mover", pos)) = split_pos(label) {
+            movers.push((pos, node));
+        } else if let Some(("path", pos)) = split_pos(label) {
+            paths.insert(pos, load_path(node, offset).ok_or_else(invalid)?);
+        } else if let Some(("planet", pos)) = split_pos(label) {
+            planets.push((pos, load_circle(node, offset).ok_or_else(invalid)?));
         } else if label == "name" {
             name = node
                 .first_child()
                 .and_then(|n| n.text())
                 .map(|text| text.to_string());
         } else if label != "Screen" {
-            println!("Unknown {:?}", node);
+            debug!("Unknown {:?}", node);
         }
     }
-    println!("Finished loading level");
+    // The movers are linked to the path with the same number.
+    let movers = movers
+        .into_iter()
+        .map(|(pos, node)| {
+            let mover = load_mover(node, offset, paths.remove(&pos));
+            Ok((pos, mover.ok_or_else(|| format!("invalid mover-{}", pos))?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    info!("Finished loading level");
 
-    Some(Level {
-        name: name?,
-        pad: pad?,
+    Ok(Level {
+        name: name.ok_or("missing name")?,
+        pad: pad.ok_or("missing launch-pad")?,
+        landing,
         goals: sort_vec(goals),
+        checkpoints: sort_vec(checkpoints),
+        crates: sort_vec(crates),
+        black_holes: sort_vec(black_holes),
+        planets: sort_vec(planets),
         walls,
+        bumpers,
+        traps,
+        movers: sort_vec(movers),
+        physics,
     })
 }
 
@@ -79,10 +292,15 @@ fn split_pos(value: &str) -> Option<(&str, usize)> {
         .and_then(|(key, pos)| pos.parse().ok().map(|pos| (key, pos)))
 }
 
+/// A bad level is not skipped, because that would shift the index of the next levels, which
+/// keys the highscores, the ghosts and the replays.
 fn load_top_level(node: &Node) -> Option<(usize, Level)> {
     let label = get_attr(node, "label")?;
     if let Some(("Level", pos)) = split_pos(label) {
-        Some((pos, load_level(node)?))
+        match load_level(node) {
+            Ok(level) => Some((pos, level)),
+            Err(e) => panic!("Invalid {}: {}", label, e),
+        }
     } else {
         None
     }
@@ -107,3 +325,90 @@ pub fn load() -> Levels {
         panic!("Couln't find svg root node")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Call the loader with the first element of the svg snippet.
+    fn with_node<T>(svg: &str, f: impl FnOnce(&Node) -> T) -> T {
+        let doc = Document::parse(svg).unwrap();
+        f(&doc.root_element())
+    }
+
+    #[test]
+    fn physics_attributes_are_parsed() {
+        let svg = r#"<g gravity="0 -0.005" damping=" 0.95" thrust-power="0.01,0.013" control="lander" fuel="600"/>"#;
+        let physics = with_node(svg, load_physics).unwrap();
+        assert_eq!(physics.gravity, Vec2::new(0.0, -0.005));
+        assert_eq!(physics.damping, 0.95);
+        assert_eq!(physics.thrust_power, Vec2::new(0.01, 0.013));
+        assert_eq!(physics.control, Control::Lander);
+        assert_eq!(physics.fuel, Some(600));
+        assert_eq!(
+            with_node("<g/>", load_physics).unwrap().gravity,
+            PhysicsParams::default().gravity
+        );
+    }
+
+    #[test]
+    fn bad_attributes_are_reported() {
+        let error = |svg| with_node(svg, load_physics).unwrap_err();
+        assert_eq!(error(r#"<g gravity="0"/>"#), "invalid gravity '0'");
+        assert_eq!(error(r#"<g gravity="0 1 2"/>"#), "invalid gravity '0 1 2'");
+        assert_eq!(error(r#"<g control="fly"/>"#), "invalid control 'fly'");
+        let error = |svg| with_node(svg, load_landing).unwrap_err();
+        assert_eq!(
+            error(r#"<g landing-rest="-1"/>"#),
+            "invalid landing-rest '-1'"
+        );
+    }
+
+    #[test]
+    fn landing_attributes_are_parsed() {
+        let svg = r#"<g landing-speed="none" landing-drift="0.02" landing-rest="30"/>"#;
+        let landing = with_node(svg, load_landing).unwrap();
+        assert_eq!(landing.max_speed, None);
+        assert_eq!(landing.max_drift, Some(0.02));
+        assert_eq!(landing.rest_frames, 30);
+    }
+
+    #[test]
+    fn paths_are_parsed() {
+        let load = |d: &str| {
+            let svg = format!(r#"<path d="{}"/>"#, d);
+            with_node(&svg, |node| load_path(*node, Vec2::new(10.0, 0.0)))
+        };
+        assert_eq!(
+            load("m 10,20 30,0 0,15 z"),
+            Some((
+                vec![
+                    Vec2::new(0.0, 20.0),
+                    Vec2::new(30.0, 20.0),
+                    Vec2::new(30.0, 35.0)
+                ],
+                true
+            ))
+        );
+        assert_eq!(
+            load("M 10 20 H 50 V 0"),
+            Some((
+                vec![
+                    Vec2::new(0.0, 20.0),
+                    Vec2::new(40.0, 20.0),
+                    Vec2::new(40.0, 0.0)
+                ],
+                false
+            ))
+        );
+        assert_eq!(load("M 0,0 C 1,1 2,2 3,3"), None);
+        assert_eq!(load("M 0,x"), None);
+    }
+
+    #[test]
+    fn every_level_is_loaded() {
+        let levels = load().0;
+        assert!(!levels.is_empty());
+        assert!(levels.iter().all(|level| !level.name.is_empty()));
+    }
+}
@@ -19,9 +19,14 @@ mod game_status;
 mod audio;
 mod entities;
 mod events;
-mod level;
+pub mod level;
 pub mod level_loader;
+pub mod physics;
+pub mod replay;
 mod resources;
+mod tas;
+pub mod tas_script;
+pub mod thrust_codec;
 mod ui;
 
 mod boot {
@@ -53,6 +58,7 @@ pub fn moonracer_main() {
         .add_plugins(resources::save::Plug)
         .add_plugins(app_status::Plug)
         .add_plugins(game_status::Plug)
+        .add_plugins(tas::Plug)
         //.add_plugins(LogDiagnosticsPlugin::default())
         //.add_plugins(FrameTimeDiagnosticsPlugin::default())
         .run();
@@ -0,0 +1,1303 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module implements the ship physics.
+//!
+//! The simulation does not depend on the ECS, so that it can be used to compute the ghost,
+//! to test the trajectories, or by external tools without running an App.
+//!
+//! The simulation is deterministic across platforms, so that a replay recorded with the wasm build
+//! gives the same result natively. It only uses the IEEE 754 basic operations (+, -, *, / and sqrt),
+//! which are correctly rounded everywhere, in a fixed order. Rust does not fuse them into FMA
+//! instructions, and the libm functions, like `sin` or `powf`, are replaced by [sin_cos].
+
+use std::ops::RangeInclusive;
+
+use bevy::math::Vec2;
+
+use crate::entities::ship::{SHIP_RADIUS, SHIP_SIZE};
+
+/// The number of steps per second.
+pub const TICK_RATE: u32 = 60;
+
+/// The version of the simulation rules, to be increased when a change makes the recorded replays
+/// produce a different trajectory.
+pub const VERSION: u16 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WallKind {
+    /// The ship stops when hitting the wall.
+    Solid,
+    /// The ship bounces, its velocity is reflected and scaled by the restitution coefficient.
+    Bumper { restitution: f32 },
+    /// The ship is destroyed when touching the wall.
+    Trap,
+}
+
+/// A rectangle the ship can't go through, in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wall {
+    pub center: Vec2,
+    pub size: Vec2,
+    pub kind: WallKind,
+}
+
+impl Wall {
+    pub fn new(center: Vec2, size: Vec2) -> Self {
+        Self {
+            center,
+            size,
+            kind: WallKind::Solid,
+        }
+    }
+    pub fn bumper(center: Vec2, size: Vec2, restitution: f32) -> Self {
+        Self {
+            center,
+            size,
+            kind: WallKind::Bumper { restitution },
+        }
+    }
+    pub fn trap(center: Vec2, size: Vec2) -> Self {
+        Self {
+            center,
+            size,
+            kind: WallKind::Trap,
+        }
+    }
+    pub fn restitution(&self) -> f32 {
+        match self.kind {
+            WallKind::Solid | WallKind::Trap => 0.0,
+            WallKind::Bumper { restitution } => restitution,
+        }
+    }
+    pub fn top(&self) -> f32 {
+        self.center.y + self.size.y / 2.0
+    }
+    pub fn bottom(&self) -> f32 {
+        self.center.y - self.size.y / 2.0
+    }
+    pub fn left(&self) -> f32 {
+        self.center.x - self.size.x / 2.0
+    }
+    pub fn right(&self) -> f32 {
+        self.center.x + self.size.x / 2.0
+    }
+}
+
+/// A wall that follows a path, its position only depends on the frame count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mover {
+    /// The wall at the start of the path.
+    pub wall: Wall,
+    /// The points the wall center goes through, relative to the first one.
+    pub path: Vec<Vec2>,
+    /// The number of frames to go back to the start of the path.
+    pub period: usize,
+    /// When set the path is a loop, otherwise the wall goes back and forth.
+    pub looped: bool,
+}
+
+impl Mover {
+    /// The position of the wall at the given frame.
+    pub fn at(&self, frame: usize) -> Wall {
+        Wall {
+            center: self.wall.center + self.offset(frame),
+            ..self.wall
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let closing = self.path.last().zip(self.path.first());
+        let closing = closing.filter(|_| self.looped).map(|(a, b)| (*a, *b));
+        self.path.windows(2).map(|w| (w[0], w[1])).chain(closing)
+    }
+
+    fn offset(&self, frame: usize) -> Vec2 {
+        let length: f32 = self.segments().map(|(a, b)| a.distance(b)).sum();
+        if self.period == 0 || length == 0.0 {
+            return Vec2::ZERO;
+        }
+        let phase = (frame % self.period) as f32 / self.period as f32;
+        let phase = if self.looped {
+            phase
+        } else {
+            1.0 - (2.0 * phase - 1.0).abs()
+        };
+        // Walk the path until the travelled distance is reached.
+        let mut distance = phase * length;
+        for (a, b) in self.segments() {
+            let segment = a.distance(b);
+            if distance <= segment && segment > 0.0 {
+                return a.lerp(b, distance / segment) - self.path[0];
+            }
+            distance -= segment;
+        }
+        self.segments()
+            .last()
+            .map_or(Vec2::ZERO, |(_, b)| b - self.path[0])
+    }
+}
+
+/// A round body that attracts the ship, and that the ship can't go through, in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Planet {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Planet {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+/// How the thrust input drives the ship.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// The input is the direction of the thrust.
+    #[default]
+    Direct,
+    /// The horizontal input rotates the ship, and the up input fires the main engine along the heading.
+    Lander,
+}
+
+/// The rotation of the ship per frame in [Control::Lander] mode, in radians.
+pub const ROTATION_SPEED: f32 = 0.08;
+
+/// The sine and cosine of the angle, computed with the basic operations only, because the std
+/// implementation depends on the platform.
+pub fn sin_cos(angle: f32) -> (f32, f32) {
+    use std::f64::consts::{FRAC_2_PI, FRAC_PI_2};
+    // Reduce the angle to [-PI/4, PI/4], then use the Taylor series which is precise enough there.
+    let angle = angle as f64;
+    let quadrant = (angle * FRAC_2_PI).round();
+    let x = angle - quadrant * FRAC_PI_2;
+    let x2 = x * x;
+    let sin = x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0))));
+    let cos = 1.0
+        - x2 / 2.0 * (1.0 - x2 / 12.0 * (1.0 - x2 / 30.0 * (1.0 - x2 / 56.0 * (1.0 - x2 / 90.0))));
+    let (sin, cos) = (sin as f32, cos as f32);
+    match (quadrant as i64).rem_euclid(4) {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    }
+}
+
+/// The rules of the simulation, in screen unit per frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsParams {
+    pub gravity: Vec2,
+    pub damping: f32,
+    pub thrust_power: Vec2,
+    /// When set, the ship crashes when it hits a wall faster than this speed.
+    pub crash_speed: Option<f32>,
+    /// The attraction of a planet of radius 1 at a distance of 1.
+    pub planet_gravity: f32,
+    pub control: Control,
+    /// The number of frames the engine can fire, unlimited when unset.
+    pub fuel: Option<usize>,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> Self {
+        Self {
+            gravity: Vec2::new(0.0, -0.01),
+            damping: 0.90,
+            thrust_power: Vec2::new(0.01, 0.013),
+            crash_speed: None,
+            planet_gravity: 0.001,
+            control: Control::Direct,
+            fuel: None,
+        }
+    }
+}
+
+/// The size of the broadphase grid cells, in screen unit.
+pub const GRID_CELL_SIZE: f32 = 0.5;
+
+/// A uniform grid of the static walls, so that the ship is only tested against the nearby walls.
+#[derive(Debug, Default, Clone)]
+pub struct Grid {
+    origin: Vec2,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    /// The index of the walls overlapping each cell, in increasing order.
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    pub fn new(walls: &[Wall], cell_size: f32) -> Self {
+        let bounds = |wall: &Wall| (wall.center - wall.size / 2.0, wall.center + wall.size / 2.0);
+        let Some((min, max)) = walls
+            .iter()
+            .map(bounds)
+            .reduce(|(min, max), (wmin, wmax)| (min.min(wmin), max.max(wmax)))
+        else {
+            return Self::default();
+        };
+        let columns = ((max.x - min.x) / cell_size) as usize + 1;
+        let rows = ((max.y - min.y) / cell_size) as usize + 1;
+        let mut grid = Self {
+            origin: min,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![vec![]; columns * rows],
+        };
+        for (idx, wall) in walls.iter().enumerate() {
+            let (min, max) = bounds(wall);
+            if let Some((xs, ys)) = grid.cells_range(min, max) {
+                for y in ys {
+                    for x in xs.clone() {
+                        grid.cells[y * columns + x].push(idx);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// The cells overlapping the box, clamped to the grid.
+    fn cells_range(
+        &self,
+        min: Vec2,
+        max: Vec2,
+    ) -> Option<(RangeInclusive<usize>, RangeInclusive<usize>)> {
+        let cell = |pos: Vec2| ((pos - self.origin) / self.cell_size).floor();
+        let (min, max) = (cell(min), cell(max));
+        let last = Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0);
+        if self.cells.is_empty() || max.cmplt(Vec2::ZERO).any() || min.cmpgt(last).any() {
+            return None;
+        }
+        let (min, max) = (min.max(Vec2::ZERO), max.min(last));
+        Some((
+            min.x as usize..=max.x as usize,
+            min.y as usize..=max.y as usize,
+        ))
+    }
+
+    /// The index of the walls that may overlap the box, in increasing order.
+    pub fn query(&self, min: Vec2, max: Vec2) -> Vec<usize> {
+        let mut walls = vec![];
+        if let Some((xs, ys)) = self.cells_range(min, max) {
+            for y in ys {
+                for x in xs.clone() {
+                    walls.extend(&self.cells[y * self.columns + x]);
+                }
+            }
+        }
+        walls.sort_unstable();
+        walls.dedup();
+        walls
+    }
+}
+
+/// The static content of a level that the ship interacts with.
+#[derive(Debug, Default, Clone)]
+pub struct World {
+    /// The static walls, they must not be modified after [World::new] which builds the grid.
+    pub walls: Vec<Wall>,
+    pub grid: Grid,
+    /// The position of the crates to pick up, at most [MAX_CRATES].
+    pub crates: Vec<Vec2>,
+    /// The position of the black holes that reverse the gravity.
+    pub black_holes: Vec<Vec2>,
+    pub planets: Vec<Planet>,
+    pub movers: Vec<Mover>,
+    pub params: PhysicsParams,
+}
+
+pub const BLACK_HOLE_RADIUS: f32 = 0.1;
+
+pub const CRATE_SIZE: f32 = 0.08;
+/// The mass added to the ship by each crate.
+pub const CRATE_MASS: f32 = 0.5;
+/// The number of crates that fits in [ShipState::cargo].
+pub const MAX_CRATES: usize = 64;
+
+/// The simulated state of the ship.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ShipState {
+    pub pos: Vec2,
+    pub velocity: Vec2,
+    /// The number of steps since the start, which sets the position of the moving walls.
+    pub frame: usize,
+    /// The angle of the ship, counterclockwise from the up direction, in radians.
+    pub heading: f32,
+    /// The number of frames the engine fired.
+    pub fuel_used: usize,
+    /// The crates carried by the ship, as a bit set of the world crates index.
+    pub cargo: u64,
+    /// True when the gravity is reversed by a black hole.
+    pub gravity_flipped: bool,
+}
+
+impl ShipState {
+    /// Create a ship at rest, `pos` being the center of the ship.
+    pub fn new(pos: Vec2) -> Self {
+        Self {
+            pos,
+            velocity: Vec2::ZERO,
+            frame: 0,
+            heading: 0.0,
+            fuel_used: 0,
+            cargo: 0,
+            gravity_flipped: false,
+        }
+    }
+
+    pub fn carries(&self, crate_idx: usize) -> bool {
+        self.cargo & (1 << crate_idx) != 0
+    }
+
+    pub fn cargo_count(&self) -> usize {
+        self.cargo.count_ones() as usize
+    }
+
+    /// The ship mass, which reduces the thrust acceleration.
+    pub fn mass(&self) -> f32 {
+        1.0 + CRATE_MASS * self.cargo_count() as f32
+    }
+
+    /// The number of frames the engine can still fire, when the fuel is limited.
+    pub fn fuel_left(&self, params: &PhysicsParams) -> Option<usize> {
+        params.fuel.map(|fuel| fuel.saturating_sub(self.fuel_used))
+    }
+
+    pub fn gravity(&self, params: &PhysicsParams) -> Vec2 {
+        if self.gravity_flipped {
+            -params.gravity
+        } else {
+            params.gravity
+        }
+    }
+}
+
+/// An obstacle of the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collider {
+    /// The index of the wall in [World::walls_at], the moving walls come after the static ones.
+    Wall(usize),
+    /// The index of the planet in the world.
+    Planet(usize),
+}
+
+/// An obstacle touched by the ship during a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub collider: Collider,
+    /// The fraction of the frame at which the ship touched the wall.
+    pub time: f32,
+    /// The normal of the surface that was hit, pointing toward the ship.
+    pub normal: Vec2,
+    /// The speed of the ship toward the obstacle when it was hit.
+    pub speed: f32,
+}
+
+/// The reason why the ship got destroyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crash {
+    /// A wall was hit above the crash speed.
+    Impact,
+    /// A trap was touched.
+    Trap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    pub ship: ShipState,
+    /// The obstacles touched by the ship, in the order they were hit.
+    pub contacts: Vec<Contact>,
+    pub crash: Option<Crash>,
+}
+
+/// The maximum number of walls the ship can slide on in a single step.
+const MAX_SWEEPS: usize = 4;
+
+/// The distance below which the ship is considered touching a wall, instead of overlapping it.
+const SUPPORT_EPSILON: f32 = 1e-4;
+
+impl Wall {
+    /// Place the ship against the side of the wall and stop, or reflect, its motion toward it.
+    /// Returns the impact speed.
+    fn hit(&self, normal: Vec2, pos: &mut Vec2, velocity: &mut Vec2) -> f32 {
+        let speed = -velocity.dot(normal);
+        let axis = if normal.x != 0.0 { 0 } else { 1 };
+        if speed > 0.0 || self.restitution() == 0.0 {
+            velocity[axis] = normal[axis] * speed.max(0.0) * self.restitution();
+        }
+        pos[axis] = if normal.x < 0.0 {
+            self.left() - SHIP_RADIUS
+        } else if normal.x > 0.0 {
+            self.right() + SHIP_RADIUS
+        } else if normal.y > 0.0 {
+            self.top() + SHIP_RADIUS
+        } else {
+            self.bottom() - SHIP_RADIUS
+        };
+        speed
+    }
+
+    /// True when the ship stands on the wall, against the gravity.
+    fn supports(&self, pos: Vec2, gravity: Vec2) -> bool {
+        let floor = if gravity.y > 0.0 {
+            self.bottom() - SHIP_RADIUS
+        } else {
+            self.top() + SHIP_RADIUS
+        };
+        (pos.y - floor).abs() < SUPPORT_EPSILON
+            && (pos.x - self.center.x).abs() < self.size.x / 2.0 + SHIP_RADIUS
+    }
+
+    /// The side to push the ship through to stop overlapping the wall, with the overlap depth.
+    /// The side with the smallest overlap is used, even when the ship is fully inside the wall.
+    pub fn penetration(&self, pos: Vec2) -> Option<(Vec2, f32)> {
+        let offset = pos - self.center;
+        let overlap = self.size / 2.0 + SHIP_RADIUS - offset.abs();
+        if overlap.x <= SUPPORT_EPSILON || overlap.y <= SUPPORT_EPSILON {
+            return None;
+        }
+        // On equal overlap the vertical side is used, so that a ship in a corner lands.
+        let (axis, depth) = if overlap.y <= overlap.x {
+            (1, overlap.y)
+        } else {
+            (0, overlap.x)
+        };
+        let mut normal = Vec2::ZERO;
+        normal[axis] = if offset[axis] < 0.0 { -1.0 } else { 1.0 };
+        Some((normal, depth))
+    }
+
+    /// Sweep the ship center along `delta` against the wall grown by the ship size.
+    /// Returns the time of impact in [0, 1] with the normal of the side that was hit.
+    fn sweep(&self, pos: Vec2, delta: Vec2) -> Option<(f32, Vec2)> {
+        let half_size = self.size / 2.0 + SHIP_RADIUS;
+        let min = self.center - half_size;
+        let max = self.center + half_size;
+        let mut entry = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        let mut normal = Vec2::ZERO;
+        // The vertical axis comes first so that an exact corner hit lands the ship.
+        for axis in [1, 0] {
+            if delta[axis] == 0.0 {
+                if pos[axis] <= min[axis] || pos[axis] >= max[axis] {
+                    return None;
+                }
+            } else {
+                let t1 = (min[axis] - pos[axis]) / delta[axis];
+                let t2 = (max[axis] - pos[axis]) / delta[axis];
+                let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+                if near > entry {
+                    entry = near;
+                    normal = Vec2::ZERO;
+                    normal[axis] = -delta[axis].signum();
+                }
+                exit = exit.min(far);
+            }
+        }
+        // A negative entry means the ship already overlaps the wall, which is resolved after the sweep.
+        if entry < exit && (0.0..=1.0).contains(&entry) {
+            Some((entry, normal))
+        } else {
+            None
+        }
+    }
+}
+
+impl Planet {
+    /// The attraction toward the planet center, which decreases with the square of the distance.
+    fn pull(&self, pos: Vec2, strength: f32) -> Vec2 {
+        let offset = self.center - pos;
+        // The pull is the strongest at the surface, the ship can't get closer anyway.
+        let distance_squared = offset.length_squared().max(self.radius * self.radius);
+        offset.normalize_or_zero() * strength * self.radius / distance_squared
+    }
+
+    /// Push the ship out of the planet and stop its motion toward the surface.
+    /// Returns the surface normal and the impact speed.
+    fn hit(&self, pos: &mut Vec2, velocity: &mut Vec2) -> Option<(Vec2, f32)> {
+        // The point of the ship box closest to the planet center.
+        let closest = self.center.clamp(*pos - SHIP_RADIUS, *pos + SHIP_RADIUS);
+        let offset = closest - self.center;
+        let distance = offset.length();
+        if distance >= self.radius {
+            return None;
+        }
+        let normal = if distance > 0.0 {
+            let normal = offset / distance;
+            *pos += normal * (self.radius - distance);
+            normal
+        } else {
+            // The planet center is inside the ship, move the ship above the planet.
+            let normal = (*pos - self.center).try_normalize().unwrap_or(Vec2::Y);
+            *pos = self.center + normal * (self.radius + SHIP_RADIUS);
+            normal
+        };
+        let speed = -velocity.dot(normal);
+        *velocity += normal * speed.max(0.0);
+        Some((normal, speed))
+    }
+}
+
+impl World {
+    pub fn new(walls: Vec<Wall>, params: PhysicsParams) -> Self {
+        Self {
+            grid: Grid::new(&walls, GRID_CELL_SIZE),
+            walls,
+            crates: vec![],
+            black_holes: vec![],
+            planets: vec![],
+            movers: vec![],
+            params,
+        }
+    }
+
+    /// The static walls followed by the moving walls at the given frame.
+    pub fn walls_at(&self, frame: usize) -> Vec<Wall> {
+        let movers = self.movers.iter().map(|mover| mover.at(frame));
+        self.walls.iter().copied().chain(movers).collect()
+    }
+
+    /// Advance the ship by one frame.
+    pub fn step(&self, ship: &ShipState, thrust: Vec2) -> StepResult {
+        let params = &self.params;
+        let (heading, acceleration) = match params.control {
+            Control::Direct => (ship.heading, thrust * params.thrust_power),
+            Control::Lander => {
+                let heading = ship.heading - thrust.x * ROTATION_SPEED;
+                let engine = thrust.y.max(0.0) * params.thrust_power.y;
+                let (sin, cos) = sin_cos(heading);
+                (heading, Vec2::new(-sin, cos) * engine)
+            }
+        };
+        // The engine stops working when the tank is empty, but the lander can still rotate.
+        let acceleration = if ship.fuel_left(params) == Some(0) {
+            Vec2::ZERO
+        } else {
+            acceleration / ship.mass()
+        };
+        let fuel_used = ship.fuel_used + usize::from(acceleration != Vec2::ZERO);
+        let pull: Vec2 = self
+            .planets
+            .iter()
+            .map(|planet| planet.pull(ship.pos, params.planet_gravity))
+            .sum();
+        let mut velocity = params.damping * (acceleration + pull + ship.velocity);
+        let mut pos = ship.pos;
+        let mut delta = ship.gravity(params) + velocity;
+        let mut elapsed = 0.0;
+        let mut contacts = vec![];
+
+        // The moving walls are at their next position for the whole step.
+        let frame = ship.frame + 1;
+        let walls = FrameWalls {
+            world: self,
+            movers: self.movers.iter().map(|mover| mover.at(frame)).collect(),
+        };
+
+        // Carry the ship standing on a moving wall.
+        if let Some(mover) = self.movers.iter().find(|mover| {
+            mover
+                .at(ship.frame)
+                .supports(ship.pos, ship.gravity(params))
+        }) {
+            pos += mover.at(frame).center - mover.at(ship.frame).center;
+        }
+        // Push the ship out of the moving walls that ran into it.
+        let movers: Vec<usize> = walls.movers().collect();
+        push_out(
+            &walls,
+            |_| movers.clone(),
+            &mut pos,
+            &mut velocity,
+            &mut contacts,
+        );
+
+        // Move the ship to the first wall on its way, then slide along it with the remaining motion.
+        for _ in 0..MAX_SWEEPS {
+            let hit = walls
+                .near(pos.min(pos + delta), pos.max(pos + delta))
+                .into_iter()
+                .filter_map(|idx| walls.get(idx).sweep(pos, delta).map(|hit| (idx, hit)))
+                .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+            let Some((idx, (time, normal))) = hit else {
+                break;
+            };
+            let wall = walls.get(idx);
+            pos += delta * time;
+            delta *= 1.0 - time;
+            // Remove, or reflect, the remaining motion toward the wall.
+            delta -= (1.0 + wall.restitution()) * normal * delta.dot(normal).min(0.0);
+            elapsed += (1.0 - elapsed) * time;
+            let speed = wall.hit(normal, &mut pos, &mut velocity);
+            contacts.push(Contact {
+                collider: Collider::Wall(idx),
+                time: elapsed,
+                normal,
+                speed,
+            });
+        }
+        pos += delta;
+
+        // Land on the planets, which are not swept because they are large compared to the ship motion.
+        for (idx, planet) in self.planets.iter().enumerate() {
+            if let Some((normal, speed)) = planet.hit(&mut pos, &mut velocity) {
+                contacts.push(Contact {
+                    collider: Collider::Planet(idx),
+                    time: 1.0,
+                    normal,
+                    speed,
+                });
+            }
+        }
+
+        // Push the ship out of the walls it was already overlapping, e.g. when spawned on the pad.
+        push_out(
+            &walls,
+            |pos| walls.near(pos, pos),
+            &mut pos,
+            &mut velocity,
+            &mut contacts,
+        );
+
+        // Pick up the crates touched by the ship.
+        let mut cargo = ship.cargo;
+        let reach = Vec2::splat((CRATE_SIZE + SHIP_SIZE) / 2.0);
+        for (idx, crate_pos) in self.crates.iter().enumerate() {
+            if (pos - *crate_pos).abs().cmple(reach).all() {
+                cargo |= 1 << idx;
+            }
+        }
+
+        // Entering a black hole reverses the gravity, until the next one.
+        let mut gravity_flipped = ship.gravity_flipped;
+        for hole in &self.black_holes {
+            let inside = |pos: Vec2| pos.distance(*hole) <= BLACK_HOLE_RADIUS;
+            if inside(pos) && !inside(ship.pos) {
+                gravity_flipped = !gravity_flipped;
+            }
+        }
+
+        let crash = if contacts.iter().any(|contact| {
+            matches!(contact.collider, Collider::Wall(idx) if walls.get(idx).kind == WallKind::Trap)
+        }) {
+            Some(Crash::Trap)
+        } else if params
+            .crash_speed
+            .is_some_and(|max| contacts.iter().any(|contact| contact.speed > max))
+        {
+            Some(Crash::Impact)
+        } else {
+            None
+        };
+        StepResult {
+            ship: ShipState {
+                pos,
+                velocity,
+                frame,
+                heading,
+                fuel_used,
+                cargo,
+                gravity_flipped,
+            },
+            contacts,
+            crash,
+        }
+    }
+
+    /// Replay the thrust inputs from the initial state, returning the ship state of every frame.
+    pub fn simulate(&self, initial: ShipState, thrust_history: &[Vec2]) -> Vec<ShipState> {
+        let mut ship = initial;
+        thrust_history
+            .iter()
+            .map(|thrust| {
+                ship = self.step(&ship, *thrust).ship;
+                ship
+            })
+            .collect()
+    }
+}
+
+/// The walls at the frame being simulated: the static walls followed by the moving walls.
+struct FrameWalls<'a> {
+    world: &'a World,
+    movers: Vec<Wall>,
+}
+
+impl FrameWalls<'_> {
+    fn get(&self, idx: usize) -> &Wall {
+        let static_walls = &self.world.walls;
+        static_walls
+            .get(idx)
+            .unwrap_or_else(|| &self.movers[idx - static_walls.len()])
+    }
+
+    fn movers(&self) -> std::ops::Range<usize> {
+        let start = self.world.walls.len();
+        start..start + self.movers.len()
+    }
+
+    /// The index of the walls the ship may touch when its center is in the box, in increasing order.
+    fn near(&self, min: Vec2, max: Vec2) -> Vec<usize> {
+        let mut near = self.world.grid.query(min - SHIP_RADIUS, max + SHIP_RADIUS);
+        near.extend(self.movers());
+        near
+    }
+}
+
+/// The maximum number of overlapping walls resolved in a single step.
+const MAX_PUSHES: usize = 8;
+
+/// Push the ship out of the candidate walls, stopping its motion toward them.
+/// The deepest overlap is resolved first, then the overlaps are checked again, so that the result
+/// does not depend on the order of the walls.
+fn push_out(
+    walls: &FrameWalls,
+    candidates: impl Fn(Vec2) -> Vec<usize>,
+    pos: &mut Vec2,
+    velocity: &mut Vec2,
+    contacts: &mut Vec<Contact>,
+) {
+    for _ in 0..MAX_PUSHES {
+        let deepest = candidates(*pos)
+            .into_iter()
+            .filter_map(|idx| walls.get(idx).penetration(*pos).map(|hit| (idx, hit)))
+            .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1).then(b.0.cmp(&a.0)));
+        let Some((idx, (normal, _))) = deepest else {
+            break;
+        };
+        let speed = walls.get(idx).hit(normal, pos, velocity);
+        contacts.push(Contact {
+            collider: Collider::Wall(idx),
+            time: 1.0,
+            normal,
+            speed,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::default;
+
+    /// Step a ship launched from the origin with the given velocity.
+    fn fire(walls: Vec<Wall>, velocity: Vec2) -> StepResult {
+        let ship = ShipState {
+            velocity,
+            ..default()
+        };
+        World::new(walls, default()).step(&ship, Vec2::ZERO)
+    }
+
+    #[test]
+    fn thin_wall_stops_fast_ship() {
+        let wall = Wall::new(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0));
+        let result = fire(vec![wall], Vec2::new(5.0, 0.0));
+        assert_eq!(result.ship.pos.x, wall.left() - SHIP_RADIUS);
+        assert_eq!(result.ship.velocity.x, 0.0);
+        assert_eq!(result.contacts.len(), 1);
+        assert_eq!(result.contacts[0].normal, Vec2::NEG_X);
+    }
+
+    #[test]
+    fn thin_pad_stops_falling_ship() {
+        // The launch pad is 0.5 unit in the svg, that is 0.04 on screen.
+        let pad = Wall::new(Vec2::new(0.0, -1.0), Vec2::new(0.5, 0.04));
+        let result = fire(vec![pad], Vec2::new(0.0, -3.0));
+        assert_eq!(result.ship.pos.y, pad.top() + SHIP_RADIUS);
+        assert_eq!(result.ship.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn nearest_wall_is_hit_first() {
+        let near = Wall::new(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0));
+        let far = Wall::new(Vec2::new(2.0, 0.0), Vec2::new(0.01, 1.0));
+        for walls in [vec![near, far], vec![far, near]] {
+            let result = fire(walls, Vec2::new(5.0, 0.0));
+            assert_eq!(result.ship.pos.x, near.left() - SHIP_RADIUS);
+            assert_eq!(result.contacts.len(), 1);
+        }
+    }
+
+    #[test]
+    fn ship_slides_along_the_floor_into_a_wall() {
+        let floor = Wall::new(Vec2::new(0.0, -0.5), Vec2::new(10.0, 0.01));
+        let wall = Wall::new(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0));
+        let result = fire(vec![wall, floor], Vec2::new(4.0, -4.0));
+        assert_eq!(result.ship.pos.y, floor.top() + SHIP_RADIUS);
+        assert_eq!(result.ship.pos.x, wall.left() - SHIP_RADIUS);
+        assert_eq!(result.ship.velocity, Vec2::ZERO);
+        let hit: Vec<Collider> = result.contacts.iter().map(|c| c.collider).collect();
+        assert_eq!(hit, vec![Collider::Wall(1), Collider::Wall(0)]);
+        assert!(result.contacts[0].time <= result.contacts[1].time);
+    }
+
+    #[test]
+    fn resting_ship_stays_on_the_floor() {
+        let floor = Wall::new(Vec2::new(0.0, -0.5), Vec2::new(10.0, 0.01));
+        let world = World::new(vec![floor], default());
+        let initial = ShipState::new(Vec2::new(0.0, floor.top() + SHIP_RADIUS));
+        let states = world.simulate(initial, &[Vec2::ZERO; 10]);
+        assert!(states
+            .iter()
+            .all(|state| state.pos == initial.pos && state.velocity == Vec2::ZERO));
+    }
+
+    #[test]
+    fn ship_lifts_off_and_lands_without_the_game() {
+        let floor = Wall::new(Vec2::new(0.0, -0.5), Vec2::new(10.0, 0.01));
+        let world = World::new(vec![floor], default());
+        let initial = ShipState::new(Vec2::new(0.0, floor.top() + SHIP_RADIUS));
+        let mut inputs = vec![Vec2::Y; 20];
+        inputs.resize(300, Vec2::ZERO);
+        let states = world.simulate(initial, &inputs);
+        assert_eq!(states.len(), inputs.len());
+        // The trajectory is the same when stepping the frames one by one.
+        let mut ship = initial;
+        for (thrust, state) in inputs.iter().zip(&states) {
+            ship = world.step(&ship, *thrust).ship;
+            assert_eq!(ship, *state);
+        }
+        assert!(states.iter().any(|state| state.pos.y > initial.pos.y + 0.1));
+        assert_eq!(ship.pos, initial.pos);
+        assert_eq!(ship.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn ship_flies_when_nothing_is_hit() {
+        let wall = Wall::new(Vec2::new(1.0, 5.0), Vec2::new(0.01, 1.0));
+        let result = fire(vec![wall], Vec2::new(5.0, 0.0));
+        assert!(result.contacts.is_empty());
+        let params = PhysicsParams::default();
+        assert_eq!(
+            result.ship.pos,
+            params.gravity + params.damping * Vec2::new(5.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn bumper_reflects_the_ship() {
+        let bumper = Wall::bumper(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0), 0.5);
+        let result = fire(vec![bumper], Vec2::new(5.0, 0.0));
+        let params = PhysicsParams::default();
+        let speed = params.damping * 5.0;
+        assert_eq!(result.ship.velocity.x, -0.5 * speed);
+        assert_eq!(result.contacts[0].speed, speed);
+        // The remaining motion is reflected away from the bumper.
+        let travel = bumper.left() - SHIP_RADIUS;
+        let remaining = speed - travel;
+        assert!((result.ship.pos.x - (travel - 0.5 * remaining)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn trap_destroys_the_ship() {
+        let wall = Wall::new(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0));
+        let trap = Wall::trap(Vec2::new(1.0, 0.0), Vec2::new(0.01, 1.0));
+        assert_eq!(fire(vec![wall], Vec2::new(5.0, 0.0)).crash, None);
+        assert_eq!(
+            fire(vec![trap], Vec2::new(5.0, 0.0)).crash,
+            Some(Crash::Trap)
+        );
+    }
+
+    #[test]
+    fn ghost_reproduces_the_bounce() {
+        let floor = Wall::new(Vec2::new(0.0, -0.5), Vec2::new(10.0, 0.01));
+        let bumper = Wall::bumper(Vec2::new(0.5, 0.0), Vec2::new(0.01, 1.0), 0.8);
+        let world = World::new(vec![floor, bumper], default());
+        let thrusts = [Vec2::X; 30];
+        let initial = ShipState::new(Vec2::new(0.0, floor.top() + SHIP_RADIUS));
+        let mut ship = initial;
+        let mut bounced = false;
+        for (thrust, ghost) in thrusts.iter().zip(world.simulate(initial, &thrusts)) {
+            let result = world.step(&ship, *thrust);
+            bounced |= result
+                .contacts
+                .iter()
+                .any(|contact| contact.collider == Collider::Wall(1));
+            ship = result.ship;
+            assert_eq!(ship, ghost);
+        }
+        assert!(bounced);
+    }
+
+    #[test]
+    fn crates_make_the_ship_heavier() {
+        let mut world = World::new(vec![], default());
+        world.crates = vec![Vec2::new(0.0, 0.05), Vec2::new(1.0, 1.0)];
+        let ship = world.step(&ShipState::default(), Vec2::ZERO).ship;
+        assert!(ship.carries(0));
+        assert!(!ship.carries(1));
+        assert_eq!(ship.mass(), 1.0 + CRATE_MASS);
+
+        let light = world.step(&ShipState::default(), Vec2::X).ship;
+        let heavy = world.step(&ship, Vec2::X).ship;
+        assert!(heavy.velocity.x < light.velocity.x);
+    }
+
+    #[test]
+    fn black_hole_reverses_the_gravity() {
+        let mut world = World::new(vec![], default());
+        world.black_holes = vec![Vec2::new(0.0, -0.155)];
+        let states = world.simulate(ShipState::default(), &[Vec2::ZERO; 20]);
+        // The ship falls into the hole, then the gravity pushes it back up and away.
+        assert!(!states[4].gravity_flipped);
+        assert!(states[5].gravity_flipped);
+        assert!(states[6].pos.y > states[5].pos.y);
+        assert!(states[5..].iter().all(|state| state.gravity_flipped));
+    }
+
+    #[test]
+    fn planet_attracts_the_ship() {
+        let params = PhysicsParams {
+            gravity: Vec2::ZERO,
+            ..default()
+        };
+        let mut world = World::new(vec![], params);
+        world.planets = vec![Planet::new(Vec2::new(1.0, 0.0), 0.5)];
+        let states = world.simulate(ShipState::default(), &[Vec2::ZERO; 10]);
+        assert!(states.windows(2).all(|w| w[1].velocity.x > w[0].velocity.x));
+        assert_eq!(states[9].pos.y, 0.0);
+    }
+
+    #[test]
+    fn ship_lands_on_the_planet() {
+        let planet = Planet::new(Vec2::new(0.0, -1.0), 0.5);
+        let mut world = World::new(vec![], default());
+        world.planets = vec![planet];
+        let states = world.simulate(ShipState::default(), &[Vec2::ZERO; 100]);
+        let ship = states.last().unwrap();
+        let surface = planet.center.y + planet.radius + SHIP_RADIUS;
+        assert!((ship.pos.y - surface).abs() < 1e-5);
+        assert!(ship.velocity.length() < 1e-5);
+        let result = world.step(ship, Vec2::ZERO);
+        assert_eq!(result.contacts[0].collider, Collider::Planet(0));
+    }
+
+    #[test]
+    fn moving_wall_follows_its_path() {
+        let mover = Mover {
+            wall: Wall::new(Vec2::ZERO, Vec2::ONE),
+            path: vec![Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)],
+            period: 100,
+            looped: false,
+        };
+        assert_eq!(mover.at(0).center, Vec2::ZERO);
+        assert_eq!(mover.at(25).center, Vec2::new(1.0, 0.0));
+        assert_eq!(mover.at(50).center, Vec2::new(1.0, 1.0));
+        assert_eq!(mover.at(75).center, Vec2::new(1.0, 0.0));
+        assert_eq!(mover.at(100).center, Vec2::ZERO);
+        let looped = Mover {
+            path: vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y],
+            looped: true,
+            ..mover
+        };
+        assert_eq!(looped.at(50).center, Vec2::ONE);
+        assert_eq!(looped.at(75).center, Vec2::Y);
+    }
+
+    #[test]
+    fn moving_wall_carries_the_ship() {
+        let floor = Wall::new(Vec2::new(0.0, -0.5), Vec2::new(4.0, 1.0));
+        let mut world = World::new(vec![], default());
+        world.movers = vec![Mover {
+            wall: floor,
+            path: vec![Vec2::ZERO, Vec2::new(1.0, 1.0)],
+            period: 200,
+            looped: false,
+        }];
+        let ship = ShipState::new(Vec2::new(0.0, SHIP_RADIUS));
+        let states = world.simulate(ship, &[Vec2::ZERO; 50]);
+        for state in states {
+            let platform = world.movers[0].at(state.frame);
+            assert!((state.pos.y - platform.top() - SHIP_RADIUS).abs() < 1e-4);
+            assert!((state.pos.x - platform.center.x).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn moving_wall_pushes_the_ship() {
+        let wall = Wall::new(Vec2::new(-1.0, 0.0), Vec2::new(1.0, 4.0));
+        let params = PhysicsParams {
+            gravity: Vec2::ZERO,
+            ..default()
+        };
+        let mut world = World::new(vec![], params);
+        world.movers = vec![Mover {
+            wall,
+            path: vec![Vec2::ZERO, Vec2::new(2.0, 0.0)],
+            period: 200,
+            looped: false,
+        }];
+        let states = world.simulate(ShipState::default(), &[Vec2::ZERO; 100]);
+        let last = states.last().unwrap();
+        assert!((last.pos.x - world.movers[0].at(last.frame).right() - SHIP_RADIUS).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lander_fires_along_its_heading() {
+        let params = PhysicsParams {
+            gravity: Vec2::ZERO,
+            control: Control::Lander,
+            ..default()
+        };
+        let world = World::new(vec![], params);
+        let ship = world.step(&ShipState::default(), Vec2::Y).ship;
+        assert_eq!(ship.velocity.x, 0.0);
+        assert!(ship.velocity.y > 0.0);
+
+        // Rotate to the right, then fire.
+        let ship = world.simulate(ShipState::default(), &[Vec2::X; 10])[9];
+        assert!((ship.heading + 10.0 * ROTATION_SPEED).abs() < 1e-5);
+        assert_eq!(ship.velocity, Vec2::ZERO);
+        let ship = world.step(&ship, Vec2::Y).ship;
+        assert!(ship.velocity.x > 0.0);
+
+        // The down input does nothing.
+        let ship = world.step(&ShipState::default(), Vec2::NEG_Y).ship;
+        assert_eq!(ship.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn engine_stops_when_the_tank_is_empty() {
+        let params = PhysicsParams {
+            gravity: Vec2::ZERO,
+            damping: 1.0,
+            fuel: Some(3),
+            ..default()
+        };
+        let world = World::new(vec![], params);
+        let states = world.simulate(ShipState::default(), &[Vec2::X; 5]);
+        assert_eq!(states[2].fuel_left(&params), Some(0));
+        assert_eq!(states[4].fuel_used, 3);
+        assert_eq!(states[4].velocity, states[2].velocity);
+    }
+
+    #[test]
+    fn thrust_is_proportional() {
+        let params = PhysicsParams {
+            gravity: Vec2::ZERO,
+            ..default()
+        };
+        let world = World::new(vec![], params);
+        let full = world.step(&ShipState::default(), Vec2::X).ship;
+        let half = world.step(&ShipState::default(), Vec2::X * 0.5).ship;
+        assert_eq!(half.velocity * 2.0, full.velocity);
+    }
+
+    #[test]
+    fn sin_cos_matches_std() {
+        for step in -1000..1000 {
+            let angle = step as f32 * 0.01;
+            let (sin, cos) = sin_cos(angle);
+            assert!((sin - angle.sin()).abs() < 1e-6, "sin {}", angle);
+            assert!((cos - angle.cos()).abs() < 1e-6, "cos {}", angle);
+        }
+    }
+
+    /// Expand the stored inputs, a list of thrust with the number of frames it is held.
+    fn inputs(stored: &[(f32, f32, usize)]) -> Vec<Vec2> {
+        stored
+            .iter()
+            .flat_map(|(x, y, count)| std::iter::repeat(Vec2::new(*x, *y)).take(*count))
+            .collect()
+    }
+
+    /// A world with every kind of obstacle.
+    fn replay_world(params: PhysicsParams) -> World {
+        let walls = vec![
+            Wall::new(Vec2::new(0.0, -2.0), Vec2::new(8.0, 0.2)),
+            Wall::new(Vec2::new(-4.0, 0.0), Vec2::new(0.2, 4.0)),
+            Wall::new(Vec2::new(4.0, 0.0), Vec2::new(0.2, 4.0)),
+            Wall::new(Vec2::new(0.0, 2.0), Vec2::new(8.0, 0.2)),
+            Wall::bumper(Vec2::new(1.5, 0.5), Vec2::new(0.3, 1.0), 0.8),
+        ];
+        let mut world = World::new(walls, params);
+        world.planets = vec![Planet::new(Vec2::new(-2.0, 0.5), 0.4)];
+        world.movers = vec![Mover {
+            wall: Wall::new(Vec2::new(2.5, -1.0), Vec2::new(0.6, 0.1)),
+            path: vec![Vec2::ZERO, Vec2::new(0.0, 1.5)],
+            period: 180,
+            looped: false,
+        }];
+        world.crates = vec![Vec2::new(-1.0, -1.5)];
+        world.black_holes = vec![Vec2::new(0.5, 1.2)];
+        world
+    }
+
+    /// Replay the inputs and compare the final position bits, which must not change between
+    /// platforms or releases, otherwise the recorded replays are no longer valid.
+    fn assert_replay(world: &World, stored: &[(f32, f32, usize)], expected: (u32, u32)) {
+        let states = world.simulate(ShipState::new(Vec2::new(0.0, -1.85)), &inputs(stored));
+        let last = states.last().unwrap();
+        assert_eq!((last.pos.x.to_bits(), last.pos.y.to_bits()), expected);
+    }
+
+    #[test]
+    fn replays_are_bit_exact() {
+        let world = replay_world(default());
+        let stored = [
+            (0.0, 1.0, 40),
+            (-1.0, 1.0, 25),
+            (-1.0, 0.0, 60),
+            (0.0, 0.0, 30),
+            (1.0, 1.0, 90),
+            (0.35, -0.6, 45),
+            (1.0, 0.0, 80),
+            (0.0, 0.0, 120),
+        ];
+        assert_replay(&world, &stored, (1081462118, 3219967181));
+    }
+
+    #[test]
+    fn lander_replays_are_bit_exact() {
+        let world = replay_world(PhysicsParams {
+            control: Control::Lander,
+            fuel: Some(200),
+            ..default()
+        });
+        let stored = [
+            (0.0, 1.0, 30),
+            (0.5, 1.0, 20),
+            (-1.0, 0.0, 15),
+            (0.0, 1.0, 60),
+            (-0.3, 0.8, 70),
+            (1.0, 1.0, 40),
+            (0.0, 0.0, 100),
+        ];
+        assert_replay(&world, &stored, (3228539432, 1053981612));
+    }
+
+    /// A world without gravity nor damping, so that the ship keeps its direction.
+    fn frictionless(walls: Vec<Wall>) -> World {
+        World::new(
+            walls,
+            PhysicsParams {
+                gravity: Vec2::ZERO,
+                damping: 1.0,
+                ..default()
+            },
+        )
+    }
+
+    #[test]
+    fn corner_approaches_never_end_inside() {
+        let wall = Wall::new(Vec2::ZERO, Vec2::splat(0.5));
+        let world = frictionless(vec![wall]);
+        let corner = wall.size / 2.0 + SHIP_RADIUS;
+        let mut targets = vec![Vec2::ZERO];
+        for sign in [
+            Vec2::ONE,
+            Vec2::NEG_ONE,
+            Vec2::X - Vec2::Y,
+            Vec2::Y - Vec2::X,
+        ] {
+            // Exactly on the corner, and just inside or outside of it on each axis.
+            for nudge in [Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y] {
+                targets.push(sign * (corner + nudge * 0.001));
+            }
+        }
+        for start in [Vec2::ONE, Vec2::X, Vec2::Y, Vec2::new(1.0, -0.3)] {
+            for rotation in [Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y] {
+                let start = rotation.rotate(start);
+                for target in &targets {
+                    for speed in [0.01, 0.1, 1.0, 5.0] {
+                        let ship = ShipState {
+                            pos: start,
+                            velocity: (*target - start).normalize() * speed,
+                            ..default()
+                        };
+                        for state in world.simulate(ship, &[Vec2::ZERO; 40]) {
+                            assert!(
+                                wall.penetration(state.pos).is_none(),
+                                "{:?} -> {:?} at {}: {:?}",
+                                start,
+                                target,
+                                speed,
+                                state.pos
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ship_does_not_squeeze_between_walls() {
+        let gap = SHIP_SIZE * 0.9;
+        let walls = vec![
+            Wall::new(Vec2::new(0.0, (1.0 + gap) / 2.0), Vec2::ONE),
+            Wall::new(Vec2::new(0.0, -(1.0 + gap) / 2.0), Vec2::ONE),
+        ];
+        for speed in [0.01, 0.1, 1.0] {
+            for y in [-gap / 2.0, 0.0, gap / 2.0] {
+                let ship = ShipState {
+                    pos: Vec2::new(-1.0, y),
+                    velocity: Vec2::X * speed,
+                    ..default()
+                };
+                let states = frictionless(walls.clone()).simulate(ship, &[Vec2::ZERO; 200]);
+                assert!(states.iter().all(|state| state.pos.x <= -0.5 - SHIP_RADIUS));
+            }
+        }
+    }
+
+    #[test]
+    fn ship_inside_a_wall_goes_out_the_nearest_side() {
+        let wall = Wall::new(Vec2::ZERO, Vec2::new(2.0, 0.4));
+        let ship = ShipState::new(Vec2::new(0.3, 0.05));
+        let result = frictionless(vec![wall]).step(&ship, Vec2::ZERO);
+        assert_eq!(result.ship.pos, Vec2::new(0.3, wall.top() + SHIP_RADIUS));
+        assert_eq!(result.contacts[0].normal, Vec2::Y);
+    }
+
+    #[test]
+    fn overlapping_walls_do_not_depend_on_the_order() {
+        let walls = vec![
+            Wall::new(Vec2::new(-0.2, 0.0), Vec2::new(0.5, 0.5)),
+            Wall::new(Vec2::new(0.1, -0.1), Vec2::new(0.5, 0.5)),
+            Wall::new(Vec2::new(0.0, -0.4), Vec2::new(2.0, 0.2)),
+        ];
+        let ship = ShipState::new(Vec2::new(0.05, 0.1));
+        let result = frictionless(walls.clone()).step(&ship, Vec2::ZERO);
+        for wall in &walls {
+            assert!(wall.penetration(result.ship.pos).is_none());
+        }
+        let reversed = walls.into_iter().rev().collect();
+        assert_eq!(
+            frictionless(reversed).step(&ship, Vec2::ZERO).ship,
+            result.ship
+        );
+    }
+
+    #[test]
+    fn grid_finds_the_walls_in_the_box() {
+        let walls: Vec<Wall> = (0..200)
+            .map(|idx| {
+                let idx = idx as f32;
+                let center = Vec2::new((idx * 0.37) % 8.0 - 4.0, (idx * 0.61) % 5.0 - 2.5);
+                Wall::new(center, Vec2::new(0.05 + (idx * 0.13) % 0.8, 0.05))
+            })
+            .collect();
+        let grid = Grid::new(&walls, GRID_CELL_SIZE);
+        for idx in 0..100 {
+            let min = Vec2::new(idx as f32 * 0.09 - 4.5, idx as f32 * 0.05 - 2.8);
+            let max = min + Vec2::new(0.3, 0.2);
+            let overlapping = walls.iter().enumerate().filter(|(_, wall)| {
+                (wall.center - (min + max) / 2.0)
+                    .abs()
+                    .cmple((wall.size + max - min) / 2.0)
+                    .all()
+            });
+            let found = grid.query(min, max);
+            for (wall_idx, _) in overlapping {
+                assert!(found.contains(&wall_idx), "{} not in {:?}", wall_idx, min);
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module is an example tool to measure the physics speed with many walls.
+//! Run with `cargo run --release --example physics-bench`
+use std::time::Instant;
+
+use bevy::math::Vec2;
+use bevy_moonracer::physics::{Grid, ShipState, Wall, World, GRID_CELL_SIZE};
+
+const STEPS: usize = 20_000;
+
+/// A closed box filled with a deterministic pattern of small walls.
+fn walls(count: usize) -> Vec<Wall> {
+    let mut walls = vec![
+        Wall::new(Vec2::new(0.0, -2.5), Vec2::new(8.85, 0.1)),
+        Wall::new(Vec2::new(0.0, 2.5), Vec2::new(8.85, 0.1)),
+        Wall::new(Vec2::new(-4.4, 0.0), Vec2::new(0.1, 5.0)),
+        Wall::new(Vec2::new(4.4, 0.0), Vec2::new(0.1, 5.0)),
+    ];
+    for idx in 0..count {
+        let idx = idx as f32;
+        let center = Vec2::new((idx * 0.37) % 8.0 - 4.0, (idx * 0.61) % 4.6 - 2.3);
+        walls.push(Wall::new(center, Vec2::new(0.04, 0.04)));
+    }
+    walls
+}
+
+/// The thrust pattern of a ship bouncing around the box.
+fn inputs() -> Vec<Vec2> {
+    let directions = [Vec2::Y, Vec2::ONE, Vec2::X, Vec2::NEG_ONE, Vec2::NEG_X];
+    (0..STEPS).map(|idx| directions[(idx / 37) % 5]).collect()
+}
+
+fn measure(world: &World, inputs: &[Vec2]) -> f64 {
+    let start = Instant::now();
+    let states = world.simulate(ShipState::default(), inputs);
+    let elapsed = start.elapsed().as_nanos() as f64 / inputs.len() as f64;
+    assert_eq!(states.len(), inputs.len());
+    elapsed
+}
+
+fn main() {
+    let inputs = inputs();
+    println!("walls   brute force   grid   (ns per step)");
+    for count in [10, 100, 300, 1000, 3000] {
+        let world = World::new(walls(count), Default::default());
+        // A single cell contains every walls, which is the same as testing them all.
+        let brute = World {
+            grid: Grid::new(&world.walls, f32::MAX),
+            ..world.clone()
+        };
+        println!(
+            "{:5} {:13.0} {:6.0}",
+            count,
+            measure(&brute, &inputs),
+            measure(&world, &inputs)
+        );
+    }
+    println!("cell size: {}", GRID_CELL_SIZE);
+}
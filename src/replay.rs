@@ -0,0 +1,401 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module implements the replay file format.
+//!
+//! A replay only contains the inputs of a run, the trajectory is re-computed by the [physics](crate::physics)
+//! simulation. Thus the header records everything the simulation depends on, so that a replay of
+//! another level or of an older game version is rejected instead of producing a desynced ghost.
+//!
+//! The `.mrreplay` files are little-endian:
+//!
+//! - the `MRRP` magic bytes and the format version (u16),
+//! - the physics version (u16) and the tick rate (u32),
+//! - the level hash (u64) and the difficulty key (u8 length followed by the utf-8 bytes),
+//! - the [RunKind] (u8),
+//! - the thrust history in the [thrust_codec] format, until the end of the file.
+//!
+//! A replay can also be shared as a string, which is the url safe base64 of the file.
+
+use std::fmt;
+
+use base64::Engine;
+use bevy::math::Vec2;
+
+use crate::level;
+use crate::physics;
+use crate::resources::Difficulty;
+use crate::thrust_codec::{self, CodecError};
+
+/// The file extension of the replays.
+pub const EXTENSION: &str = "mrreplay";
+
+const MAGIC: &[u8; 4] = b"MRRP";
+
+/// The version of the file layout, to be increased when the encoding changes.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// How the run was made, only the normal runs compete with the highscores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunKind {
+    Normal,
+    /// The run resumed from a checkpoint.
+    Practice,
+    /// The run was made in TAS mode.
+    Tas,
+}
+
+impl RunKind {
+    const ALL: [RunKind; 3] = [RunKind::Normal, RunKind::Practice, RunKind::Tas];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunKind::Normal => "normal",
+            RunKind::Practice => "practice",
+            RunKind::Tas => "TAS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub physics_version: u16,
+    pub tick_rate: u32,
+    /// The [hash] of the level data.
+    pub level_hash: u64,
+    /// The key of the difficulty, which changes the level physics.
+    pub difficulty: String,
+    pub kind: RunKind,
+    pub thrust_history: Vec<Vec2>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    NotAReplay,
+    UnsupportedFormat(u16),
+    Truncated,
+    InvalidRunKind(u8),
+    InvalidInputs(CodecError),
+    PhysicsMismatch { expected: u16, found: u16 },
+    TickRateMismatch { expected: u32, found: u32 },
+    LevelMismatch { expected: u64, found: u64 },
+    DifficultyMismatch { expected: String, found: String },
+    UnknownDifficulty(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a replay file"),
+            ReplayError::UnsupportedFormat(version) => {
+                write!(f, "unsupported replay format version {}", version)
+            }
+            ReplayError::Truncated => write!(f, "the replay file is truncated"),
+            ReplayError::InvalidRunKind(tag) => write!(f, "invalid run kind {}", tag),
+            ReplayError::InvalidInputs(e) => write!(f, "invalid inputs: {}", e),
+            ReplayError::PhysicsMismatch { expected, found } => write!(
+                f,
+                "the replay was recorded with the physics version {}, expected {}",
+                found, expected
+            ),
+            ReplayError::TickRateMismatch { expected, found } => write!(
+                f,
+                "the replay was recorded at {} ticks per second, expected {}",
+                found, expected
+            ),
+            ReplayError::LevelMismatch { expected, found } => write!(
+                f,
+                "the replay was recorded for another level (hash {:016x}, expected {:016x})",
+                found, expected
+            ),
+            ReplayError::DifficultyMismatch { expected, found } => write!(
+                f,
+                "the replay was recorded with the {} difficulty, expected {}",
+                found, expected
+            ),
+            ReplayError::UnknownDifficulty(key) => write!(f, "unknown difficulty '{}'", key),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<CodecError> for ReplayError {
+    fn from(e: CodecError) -> Self {
+        match e {
+            CodecError::Truncated => ReplayError::Truncated,
+            e => ReplayError::InvalidInputs(e),
+        }
+    }
+}
+
+impl Replay {
+    /// Create a replay of a normal run for the current game version.
+    pub fn new(level_hash: u64, difficulty: &str, thrust_history: Vec<Vec2>) -> Self {
+        Self {
+            physics_version: physics::VERSION,
+            tick_rate: physics::TICK_RATE,
+            level_hash,
+            difficulty: difficulty.to_string(),
+            kind: RunKind::Normal,
+            thrust_history,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(32 + self.thrust_history.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(&self.physics_version.to_le_bytes());
+        data.extend_from_slice(&self.tick_rate.to_le_bytes());
+        data.extend_from_slice(&self.level_hash.to_le_bytes());
+        let difficulty = &self.difficulty.as_bytes()[..self.difficulty.len().min(255)];
+        data.push(difficulty.len() as u8);
+        data.extend_from_slice(difficulty);
+        data.push(self.kind as u8);
+        data.extend_from_slice(&thrust_codec::encode(&self.thrust_history));
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader(data);
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(ReplayError::NotAReplay);
+        }
+        let format = u16::from_le_bytes(reader.array()?);
+        if format != FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedFormat(format));
+        }
+        let physics_version = u16::from_le_bytes(reader.array()?);
+        let tick_rate = u32::from_le_bytes(reader.array()?);
+        let level_hash = u64::from_le_bytes(reader.array()?);
+        let [difficulty_len] = reader.array()?;
+        let difficulty = String::from_utf8_lossy(reader.take(difficulty_len as usize)?).into();
+        let [tag] = reader.array()?;
+        let kind = *RunKind::ALL
+            .get(tag as usize)
+            .ok_or(ReplayError::InvalidRunKind(tag))?;
+        let thrust_history = thrust_codec::decode(reader.0)?;
+        Ok(Self {
+            physics_version,
+            tick_rate,
+            level_hash,
+            difficulty,
+            kind,
+            thrust_history,
+        })
+    }
+
+    /// The shareable string of the replay.
+    pub fn to_text(&self) -> String {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(self.encode())
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, ReplayError> {
+        let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(text.trim())
+            .map_err(|_| ReplayError::NotAReplay)?;
+        Self::decode(&data)
+    }
+
+    /// Check that the replay can be played in the level.
+    pub fn check(&self, level_hash: u64, difficulty: &str) -> Result<(), ReplayError> {
+        if self.physics_version != physics::VERSION {
+            Err(ReplayError::PhysicsMismatch {
+                expected: physics::VERSION,
+                found: self.physics_version,
+            })
+        } else if self.tick_rate != physics::TICK_RATE {
+            Err(ReplayError::TickRateMismatch {
+                expected: physics::TICK_RATE,
+                found: self.tick_rate,
+            })
+        } else if self.level_hash != level_hash {
+            Err(ReplayError::LevelMismatch {
+                expected: level_hash,
+                found: self.level_hash,
+            })
+        } else if self.difficulty != difficulty {
+            Err(ReplayError::DifficultyMismatch {
+                expected: difficulty.to_string(),
+                found: self.difficulty.clone(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Decode a replay file, or its share code, and check that it belongs to the level.
+pub fn load(data: &[u8], level_hash: u64, difficulty: &str) -> Result<Replay, ReplayError> {
+    let replay = match std::str::from_utf8(data) {
+        Ok(text) if !data.starts_with(MAGIC) => Replay::from_text(text)?,
+        _ => Replay::decode(data)?,
+    };
+    replay.check(level_hash, difficulty)?;
+    Ok(replay)
+}
+
+/// Play the replay in the level without the game, using the difficulty it was recorded with.
+pub fn verify(level: &level::Level, replay: &Replay) -> Result<level::Run, ReplayError> {
+    let difficulty = Difficulty::from_key(&replay.difficulty)
+        .ok_or_else(|| ReplayError::UnknownDifficulty(replay.difficulty.clone()))?;
+    replay.check(level.hash(), &replay.difficulty)?;
+    let screen = level::Screen::default();
+    let mut world = level.world(&screen);
+    difficulty.apply(&mut world.params);
+    let ship = physics::ShipState::new(level::initial_ship_center(level, &screen));
+    Ok(level::play(
+        &world,
+        &level.objectives(&screen),
+        ship,
+        &replay.thrust_history,
+    ))
+}
+
+/// The FNV-1a hash, which is stable across platforms and compiler versions, unlike the std Hasher.
+pub fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < len {
+            return Err(ReplayError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let thrust_history = vec![
+            Vec2::ZERO,
+            Vec2::new(0.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(0.31, -0.7),
+            Vec2::new(-0.0, 1.0),
+        ];
+        Replay::new(hash(b"level"), "cadet", thrust_history)
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let replay = replay();
+        let data = replay.encode();
+        let decoded = Replay::decode(&data).unwrap();
+        assert_eq!(decoded, replay);
+        // The sign of the zero is preserved.
+        assert_eq!(decoded.thrust_history[4].x.to_bits(), (-0.0f32).to_bits());
+        // The digital runs take a single byte.
+        assert_eq!(data.len(), HEADER + 3 + 2 * 9);
+        assert_eq!(Replay::from_text(&replay.to_text()), Ok(replay));
+        assert_eq!(Replay::from_text("MRRP"), Err(ReplayError::NotAReplay));
+    }
+
+    /// The size of the header with the cadet difficulty.
+    const HEADER: usize = 4 + 2 + 2 + 4 + 8 + 6 + 1;
+
+    #[test]
+    fn the_run_kind_is_recorded() {
+        let tas = Replay {
+            kind: RunKind::Tas,
+            ..replay()
+        };
+        let mut data = tas.encode();
+        assert_eq!(Replay::decode(&data), Ok(tas));
+        data[HEADER - 1] = 3;
+        assert_eq!(Replay::decode(&data), Err(ReplayError::InvalidRunKind(3)));
+    }
+
+    #[test]
+    fn replays_of_another_level_are_rejected() {
+        let data = replay().encode();
+        assert_eq!(
+            load(&data, hash(b"other"), "cadet"),
+            Err(ReplayError::LevelMismatch {
+                expected: hash(b"other"),
+                found: hash(b"level")
+            })
+        );
+        assert!(matches!(
+            load(&data, hash(b"level"), "crash"),
+            Err(ReplayError::DifficultyMismatch { .. })
+        ));
+        assert!(load(&data, hash(b"level"), "cadet").is_ok());
+        let text = replay().to_text() + "\n";
+        assert_eq!(load(text.as_bytes(), hash(b"level"), "cadet"), Ok(replay()));
+        assert_eq!(
+            load(b"not a replay", hash(b"level"), "cadet"),
+            Err(ReplayError::NotAReplay)
+        );
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let data = replay().encode();
+        assert_eq!(Replay::decode(b"hello"), Err(ReplayError::NotAReplay));
+        assert_eq!(
+            Replay::decode(&data[..data.len() - 1]),
+            Err(ReplayError::Truncated)
+        );
+        let mut future = data.clone();
+        future[4] = 42;
+        assert_eq!(
+            Replay::decode(&future),
+            Err(ReplayError::UnsupportedFormat(42))
+        );
+        // The first run follows the header.
+        let mut invalid = data;
+        invalid[HEADER] = 9 << 4;
+        assert_eq!(
+            Replay::decode(&invalid),
+            Err(ReplayError::InvalidInputs(CodecError::InvalidDirection(9)))
+        );
+    }
+
+    #[test]
+    fn replays_are_verified_in_their_level() {
+        let level = &crate::level_loader::load().0[0];
+        // The ship stays on the launch pad without inputs.
+        let idle = Replay::new(level.hash(), "cadet", vec![Vec2::ZERO; 10]);
+        let run = verify(level, &idle).unwrap();
+        assert_eq!((run.frames, run.end), (10, None));
+        assert_eq!(run.progress.score, 0);
+
+        let unknown = Replay::new(level.hash(), "easy", vec![]);
+        assert_eq!(
+            verify(level, &unknown),
+            Err(ReplayError::UnknownDifficulty("easy".into()))
+        );
+        let other = Replay::new(hash(b"other"), "cadet", vec![]);
+        assert!(matches!(
+            verify(level, &other),
+            Err(ReplayError::LevelMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn forged_thrust_is_rejected() {
+        let forged = Replay::new(hash(b"level"), "cadet", vec![Vec2::new(100.0, 100.0); 60]);
+        assert_eq!(
+            load(&forged.encode(), hash(b"level"), "cadet"),
+            Err(ReplayError::InvalidInputs(CodecError::InvalidThrust(
+                Vec2::new(100.0, 100.0)
+            )))
+        );
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module is an example tool to check a replay without running the game.
+//! Run with `cargo run --example verify -- LEVEL FILE.mrreplay`, where LEVEL starts at 1.
+//! The replay can also be a share code, as logged by the export.
+//!
+//! The exit code is 0 when the run completes the level, 1 when the inputs end before,
+//! 2 when the ship crashes, 3 when the replay is invalid and 4 for a usage error.
+use std::process::ExitCode;
+
+use bevy_moonracer::level::End;
+use bevy_moonracer::{level_loader, replay};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (level, path) = match args.as_slice() {
+        [level, path] => (level, path),
+        _ => {
+            eprintln!("usage: verify LEVEL FILE.{}|SHARE_CODE", replay::EXTENSION);
+            return ExitCode::from(4);
+        }
+    };
+    let levels = level_loader::load().0;
+    let Some(level) = level
+        .parse::<usize>()
+        .ok()
+        .and_then(|idx| levels.get(idx.checked_sub(1)?))
+    else {
+        eprintln!("invalid level '{}', expected 1 to {}", level, levels.len());
+        return ExitCode::from(4);
+    };
+    let decoded = if path.ends_with(replay::EXTENSION) {
+        match std::fs::read(path) {
+            Ok(data) => replay::Replay::decode(&data),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return ExitCode::from(4);
+            }
+        }
+    } else {
+        replay::Replay::from_text(path)
+    };
+    let (run, kind) = match decoded.and_then(|r| Ok((replay::verify(level, &r)?, r.kind))) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::from(3);
+        }
+    };
+
+    let (result, code) = match &run.end {
+        Some(End::Completed) => ("completed".to_string(), 0),
+        None => ("incomplete".to_string(), 1),
+        Some(End::Crashed(crash)) => (format!("crashed ({:?})", crash), 2),
+        Some(End::HardLanding(reason)) => (format!("hard landing ({})", reason), 2),
+    };
+    println!("level: {}", level.name);
+    println!("run: {}", kind.label());
+    println!("result: {}", result);
+    println!("frames: {}", run.frames);
+    println!("goals: {}/{}", run.progress.score, level.goals.len());
+    println!("position: {:.4} {:.4}", run.ship.pos.x, run.ship.pos.y);
+    ExitCode::from(code)
+}
@@ -4,31 +4,308 @@
 //! This module contains the global state.
 
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::physics;
 
 pub mod save;
 
-pub const FREQ: f32 = 1.0 / 60.0;
+pub const FREQ: f32 = 1.0 / physics::TICK_RATE as f32;
+
+/// The speed above which the ship crashes in the Crash difficulty.
+pub const CRASH_SPEED: f32 = 0.05;
+
+/// The game rules selected by the player, each one has its own highscores.
+#[derive(Resource, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Difficulty {
+    #[default]
+    Cadet,
+    NoGravity,
+    NoDrag,
+    Crash,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Cadet,
+        Difficulty::NoGravity,
+        Difficulty::NoDrag,
+        Difficulty::Crash,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Cadet => "Cadet",
+            Difficulty::NoGravity => "No Gravity",
+            Difficulty::NoDrag => "No Drag",
+            Difficulty::Crash => "Crash",
+        }
+    }
+
+    /// The save store key, which is also used for the highscores.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Difficulty::Cadet => "cadet",
+            Difficulty::NoGravity => "no-gravity",
+            Difficulty::NoDrag => "no-drag",
+            Difficulty::Crash => "crash",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Difficulty::ALL.into_iter().find(|d| d.key() == key)
+    }
+
+    /// Adjust the level physics to the difficulty rules.
+    pub fn apply(&self, params: &mut physics::PhysicsParams) {
+        match self {
+            Difficulty::Cadet => {}
+            Difficulty::NoGravity => {
+                params.gravity = Vec2::ZERO;
+                params.planet_gravity = 0.0;
+            }
+            Difficulty::NoDrag => params.damping = 1.0,
+            Difficulty::Crash => params.crash_speed = Some(CRASH_SPEED),
+        }
+    }
+}
+
+/// How the gamepad left stick is converted to a proportional thrust.
+#[derive(Resource, Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct StickSettings {
+    /// The stick distance from the center below which the thrust is zero.
+    pub deadzone: f32,
+    /// The response curve exponent, above 1 gives more precision for small thrusts.
+    pub exponent: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.2,
+            exponent: 2.0,
+        }
+    }
+}
+
+impl StickSettings {
+    /// The deadzones selectable in the settings menu.
+    pub const DEADZONES: [f32; 5] = [0.1, 0.15, 0.2, 0.25, 0.3];
+    /// The curve exponents selectable in the settings menu.
+    pub const EXPONENTS: [f32; 4] = [1.0, 1.5, 2.0, 3.0];
+
+    /// Select the next deadzone, wrapping around.
+    pub fn next_deadzone(&self) -> Self {
+        Self {
+            deadzone: next_value(&Self::DEADZONES, self.deadzone),
+            ..*self
+        }
+    }
+
+    pub fn next_exponent(&self) -> Self {
+        Self {
+            exponent: next_value(&Self::EXPONENTS, self.exponent),
+            ..*self
+        }
+    }
+
+    /// The thrust for the stick position, it goes from 0 at the deadzone to the edge of the
+    /// keyboard square at full tilt, so that a diagonal is as strong as two keys.
+    pub fn thrust(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length().min(1.0);
+        if length <= self.deadzone {
+            return Vec2::ZERO;
+        }
+        let response = ((length - self.deadzone) / (1.0 - self.deadzone)).powf(self.exponent);
+        // Map the circle of the stick onto the square.
+        let edge = stick / stick.abs().max_element();
+        (edge * response).clamp(Vec2::NEG_ONE, Vec2::ONE)
+    }
+}
+
+/// The value following the current one, or the first value when it is not in the list.
+fn next_value(values: &[f32], current: f32) -> f32 {
+    let next = values
+        .iter()
+        .position(|v| *v == current)
+        .map_or(0, |i| i + 1);
+    values[next % values.len()]
+}
+
+/// The state saved when touching a checkpoint, to respawn there when practicing.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    /// The index of the checkpoint in the level.
+    pub idx: usize,
+    pub ship: physics::ShipState,
+    pub score: usize,
+    pub frame_count: usize,
+    pub thrust_history: Vec<Vec2>,
+}
+
+/// The tool assisted speedrun editor.
+#[derive(Resource, Default)]
+pub struct Tas {
+    /// True while the game is played in TAS mode, including in the pause menu.
+    pub enabled: bool,
+    /// The inputs of the run, which can be longer than the simulated frames after a rewind.
+    pub inputs: Vec<Vec2>,
+    /// The frame selected in the timeline.
+    pub selected: usize,
+    /// The number of frames to simulate on the next ticks.
+    pub pending: usize,
+}
+
+impl Tas {
+    /// The status to play the level with.
+    pub fn playing_status(&self) -> crate::app_status::AppStatus {
+        if self.enabled {
+            crate::app_status::AppStatus::Tas
+        } else {
+            crate::app_status::AppStatus::Playing
+        }
+    }
+}
+
+/// The runs that can be raced as a ghost.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum GhostKind {
+    /// The fastest run, which is saved.
+    Best,
+    /// The previous attempt, even when it crashed.
+    Last,
+    /// The run of the TAS script.
+    Script,
+    /// The run of an imported replay.
+    Imported,
+}
+
+impl GhostKind {
+    pub const ALL: [GhostKind; 4] = [
+        GhostKind::Best,
+        GhostKind::Last,
+        GhostKind::Script,
+        GhostKind::Imported,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GhostKind::Best => "Best",
+            GhostKind::Last => "Last",
+            GhostKind::Script => "Script",
+            GhostKind::Imported => "Imported",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            GhostKind::Best => Color::rgb_linear(0.5, 0.5, 5.0),
+            GhostKind::Last => Color::rgb_linear(3.0, 1.0, 0.2),
+            GhostKind::Script => Color::rgb_linear(0.3, 3.0, 0.5),
+            GhostKind::Imported => Color::rgb_linear(3.0, 0.3, 3.0),
+        }
+    }
+}
 
 pub struct Ghost {
+    pub kind: GhostKind,
     pub score: usize,
     pub frame_count: usize,
+    /// The inputs of the run, kept to save the ghost.
+    pub thrust_history: Vec<Vec2>,
     pub positions: Vec<Vec3>,
 }
 
+/// The ghosts of the current level, with at most one ghost of each kind.
+#[derive(Default)]
+pub struct Ghosts {
+    ghosts: Vec<Ghost>,
+    /// The kinds hidden from the pause menu, they stay hidden when the ghosts change.
+    hidden: HashSet<GhostKind>,
+}
+
+impl Ghosts {
+    pub fn get(&self, kind: GhostKind) -> Option<&Ghost> {
+        self.ghosts.iter().find(|ghost| ghost.kind == kind)
+    }
+
+    /// Add the ghost, replacing the previous one of the same kind.
+    pub fn set(&mut self, ghost: Ghost) {
+        self.ghosts.retain(|prev| prev.kind != ghost.kind);
+        self.ghosts.push(ghost);
+    }
+
+    pub fn clear(&mut self) {
+        self.ghosts.clear();
+    }
+
+    pub fn is_visible(&self, kind: GhostKind) -> bool {
+        !self.hidden.contains(&kind)
+    }
+
+    /// True when the run replaces the best ghost: it must complete the level faster.
+    pub fn is_new_best(&self, run: &crate::level::Run) -> bool {
+        if run.end != Some(crate::level::End::Completed) || run.frames == 0 {
+            return false;
+        }
+        match self.get(GhostKind::Best) {
+            Some(best) => {
+                best.score < run.progress.score
+                    || (best.score == run.progress.score && best.frame_count > run.frames)
+            }
+            None => true,
+        }
+    }
+
+    pub fn toggle(&mut self, kind: GhostKind) {
+        if !self.hidden.remove(&kind) {
+            self.hidden.insert(kind);
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct GameResources {
+    /// The current input, each component is in [-1, 1] and proportional when using the stick.
     pub thrust: Vec2,
     pub frame_count: usize,
-    pub score: usize,
-    pub goals: Vec<Vec2>,
-    pub launch_pad: (Vec3, Vec2),
+    pub progress: crate::level::Progress,
+    pub objectives: crate::level::Objectives,
+    pub checkpoints: Vec<Vec2>,
+    /// The last touched checkpoint, it is kept when the level restarts.
+    pub checkpoint: Option<Checkpoint>,
+    /// Set to resume from the checkpoint on the next level spawn.
+    pub respawn_at_checkpoint: bool,
+    /// True when the current run resumed from a checkpoint, which makes it a practice run that
+    /// is not eligible for the highscores and the ghost.
+    pub used_checkpoint: bool,
+    /// True when the current run was made in TAS mode, it is only eligible for the TAS records.
+    pub used_tas: bool,
+    /// The reason why the last landing failed, displayed when the level restarts.
+    pub landing_failure: Option<String>,
+    /// The result of the last replay import, displayed in the pause menu.
+    pub import_status: Option<String>,
     pub thrust_history: Vec<Vec2>,
-    pub ghost: Option<Ghost>,
+    pub ghosts: Ghosts,
+    pub world: physics::World,
+    pub ship: physics::ShipState,
+    /// The obstacles touched during the last frame.
+    pub contacts: Vec<physics::Contact>,
+
+    /// True when the ship got destroyed during the current attempt.
+    pub destroyed: bool,
+    /// The number of attempts that ended with the ship destroyed.
+    pub failed_attempts: usize,
 
     pub current_level: usize,
     pub made_highscore: bool,
     pub highscores: HashMap<usize, usize>,
+    pub made_fuel_record: bool,
+    /// The most fuel left when completing the levels that have a limited fuel.
+    pub fuel_records: HashMap<usize, usize>,
+    pub made_tas_record: bool,
+    /// The best scores of the runs made in TAS mode.
+    pub tas_records: HashMap<usize, usize>,
 }
 
 impl GameResources {
@@ -48,14 +325,109 @@ impl Default for GameResources {
         Self {
             thrust: default(),
             frame_count: 0,
-            score: 0,
-            goals: vec![],
+            progress: default(),
+            objectives: default(),
+            checkpoints: vec![],
+            checkpoint: None,
+            respawn_at_checkpoint: false,
+            used_checkpoint: false,
+            used_tas: false,
             thrust_history: vec![],
-            ghost: None,
+            ghosts: default(),
+            world: default(),
+            ship: default(),
+            contacts: vec![],
+            destroyed: false,
+            failed_attempts: 0,
             current_level: 0,
-            launch_pad: (Vec3::default(), Vec2::default()),
+            landing_failure: None,
+            import_status: None,
             made_highscore: false,
             highscores: HashMap::new(),
+            made_fuel_record: false,
+            fuel_records: HashMap::new(),
+            made_tas_record: false,
+            tas_records: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{End, Objectives, Progress, Run};
+
+    fn best(frame_count: usize) -> Ghost {
+        Ghost {
+            kind: GhostKind::Best,
+            score: 1,
+            frame_count,
+            thrust_history: vec![Vec2::Y; frame_count],
+            positions: vec![],
+        }
+    }
+
+    fn completed(frames: usize) -> Run {
+        Run {
+            ship: default(),
+            progress: Progress {
+                score: 1,
+                rest_frames: 0,
+            },
+            frames,
+            end: Some(End::Completed),
         }
     }
+
+    #[test]
+    fn a_full_stick_gives_the_keyboard_thrust() {
+        let settings = StickSettings::default();
+        let diagonal = settings.thrust(Vec2::new(-1.0, 1.0).normalize());
+        assert!((diagonal - Vec2::new(-1.0, 1.0)).abs().max_element() < 1e-5);
+        assert_eq!(settings.thrust(Vec2::Y), Vec2::Y);
+        assert_eq!(settings.thrust(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+        // The stick edges may go past the unit circle.
+        assert_eq!(settings.thrust(Vec2::new(1.0, 1.0)), Vec2::ONE);
+    }
+
+    #[test]
+    fn loading_a_level_keeps_the_saved_ghost() {
+        // The ghost update runs when the level spawns, before anything was played.
+        let world = physics::World::default();
+        let ship = physics::ShipState::new(Vec2::ZERO);
+        let spawned = crate::level::play(&world, &Objectives::default(), ship, &[]);
+        assert!(!Ghosts::default().is_new_best(&spawned));
+        let mut ghosts = Ghosts::default();
+        ghosts.set(best(200));
+        assert!(!ghosts.is_new_best(&spawned));
+        // An unfinished run is not a ghost either.
+        let unfinished = Run {
+            end: None,
+            ..completed(100)
+        };
+        assert!(!ghosts.is_new_best(&unfinished));
+    }
+
+    #[test]
+    fn only_a_faster_run_is_the_best_ghost() {
+        let mut ghosts = Ghosts::default();
+        assert!(ghosts.is_new_best(&completed(300)));
+        ghosts.set(best(200));
+        assert!(!ghosts.is_new_best(&completed(200)));
+        assert!(ghosts.is_new_best(&completed(150)));
+    }
+
+    #[test]
+    fn an_imported_ghost_is_raced_next_to_the_best() {
+        let mut ghosts = Ghosts::default();
+        ghosts.set(best(200));
+        ghosts.set(Ghost {
+            kind: GhostKind::Imported,
+            ..best(100)
+        });
+        assert_eq!(ghosts.get(GhostKind::Best).unwrap().frame_count, 200);
+        assert_eq!(ghosts.get(GhostKind::Imported).unwrap().frame_count, 100);
+        // The imported run does not count as the best of the player.
+        assert!(ghosts.is_new_best(&completed(150)));
+    }
 }
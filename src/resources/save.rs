@@ -6,34 +6,448 @@
 use bevy::prelude::*;
 
 use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
 
-use crate::events::NewHighscore;
-use crate::level::Levels;
-use crate::resources::GameResources;
+use crate::app_status::AppStatus;
+use crate::events::{
+    ExportReplay, ImportReplay, NewFuelRecord, NewGhost, NewHighscore, NewTasRecord, Script,
+};
+use crate::game_status::{compute_ghost, update_ghost, GameStatus};
+use crate::level::{initial_ship_center, End, Level, Levels, Screen};
+use crate::replay::{ReplayError, RunKind};
+use crate::resources::{Difficulty, GameResources, Ghost, GhostKind, StickSettings, Tas};
 
 pub struct Plug;
 impl Plugin for Plug {
     fn build(&self, app: &mut App) {
         app.insert_resource(PkvStore::new("MoonRacer", "Save"))
+            .init_resource::<Difficulty>()
+            .init_resource::<StickSettings>()
             .add_systems(Startup, setup)
-            .add_systems(Update, save_highscore.run_if(on_event::<NewHighscore>()));
+            .add_systems(Update, save_highscore.run_if(on_event::<NewHighscore>()))
+            .add_systems(Update, save_fuel_record.run_if(on_event::<NewFuelRecord>()))
+            .add_systems(Update, save_ghost.run_if(on_event::<NewGhost>()))
+            .add_systems(Update, save_tas_record.run_if(on_event::<NewTasRecord>()))
+            .add_systems(Update, export_replay.run_if(on_event::<ExportReplay>()))
+            .add_systems(Update, import_replay.run_if(on_event::<ImportReplay>()))
+            .add_systems(Update, handle_script.run_if(on_event::<Script>()))
+            // The ghost is replayed in the world of the level, which is created by the setup.
+            .add_systems(
+                OnEnter(GameStatus::Spawning),
+                load_ghost.after(update_ghost).after(crate::level::setup),
+            )
+            // Reload the highscores before the menus are spawned by the state transition.
+            .add_systems(
+                PreUpdate,
+                save_difficulty.run_if(resource_changed::<Difficulty>()),
+            )
+            .add_systems(
+                Update,
+                save_stick_settings.run_if(resource_changed::<StickSettings>()),
+            );
     }
 }
 
 fn save_highscore(
     mut events: EventReader<NewHighscore>,
     mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
     mut pkv: ResMut<PkvStore>,
 ) {
     for event in events.read() {
         state.highscores.insert(event.level, event.score);
-        pkv.set("cadet", &state.highscores)
+        pkv.set(difficulty.key(), &state.highscores)
             .expect("failed to store highscore");
     }
 }
 
-fn setup(mut state: ResMut<GameResources>, levels: Res<Levels>, pkv: ResMut<PkvStore>) {
-    if let Ok(times) = pkv.get::<bevy::utils::HashMap<usize, usize>>("cadet") {
+/// The fuel records are stored next to the highscores of the difficulty.
+fn fuel_key(difficulty: &Difficulty) -> String {
+    format!("{}-fuel", difficulty.key())
+}
+
+fn save_fuel_record(
+    mut events: EventReader<NewFuelRecord>,
+    mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for event in events.read() {
+        state.fuel_records.insert(event.level, event.fuel);
+        pkv.set(fuel_key(&difficulty), &state.fuel_records)
+            .expect("failed to store fuel record");
+    }
+}
+
+/// The TAS records are kept apart from the highscores.
+fn tas_key(difficulty: &Difficulty) -> String {
+    format!("{}-tas", difficulty.key())
+}
+
+fn save_tas_record(
+    mut events: EventReader<NewTasRecord>,
+    mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for event in events.read() {
+        state.tas_records.insert(event.level, event.score);
+        pkv.set(tas_key(&difficulty), &state.tas_records)
+            .expect("failed to store TAS record");
+    }
+}
+
+/// The ghost of a level is stored as the replay of the run, the positions are replayed on load.
+#[derive(Serialize, Deserialize)]
+struct SavedGhost {
+    score: usize,
+    frame_count: usize,
+    /// The [replay](crate::replay) share code, which records the level and physics versions.
+    replay: String,
+}
+
+impl SavedGhost {
+    fn new(ghost: &Ghost, level: &Level, difficulty: &Difficulty) -> Self {
+        let replay = crate::replay::Replay::new(
+            level.hash(),
+            difficulty.key(),
+            ghost.thrust_history.clone(),
+        );
+        Self {
+            score: ghost.score,
+            frame_count: ghost.frame_count,
+            replay: replay.to_text(),
+        }
+    }
+
+    /// The inputs of the ghost, unless the level or the physics changed since it was saved.
+    fn inputs(&self, level: &Level, difficulty: &Difficulty) -> Result<Vec<Vec2>, ReplayError> {
+        let replay = crate::replay::load(self.replay.as_bytes(), level.hash(), difficulty.key())?;
+        Ok(replay.thrust_history)
+    }
+}
+
+/// Each level has its own ghost key, next to the highscores of the difficulty.
+fn ghost_key(difficulty: &Difficulty, level: usize) -> String {
+    format!("{}-ghost-{}", difficulty.key(), level)
+}
+
+fn save_ghost(
+    mut events: EventReader<NewGhost>,
+    state: Res<GameResources>,
+    difficulty: Res<Difficulty>,
+    levels: Res<Levels>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for event in events.read() {
+        let Some(ghost) = state.ghosts.get(GhostKind::Best) else {
+            continue;
+        };
+        let saved = SavedGhost::new(ghost, &levels.0[event.level], &difficulty);
+        pkv.set(ghost_key(&difficulty, event.level), &saved)
+            .expect("failed to store ghost");
+    }
+}
+
+fn load_ghost(
+    mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
+    levels: Res<Levels>,
+    pkv: Res<PkvStore>,
+) {
+    let level = &levels.0[state.current_level];
+    load_best_ghost(&mut state, level, &difficulty, &pkv);
+}
+
+/// Load the saved ghost of the current level, unless the best ghost is already raced.
+/// The ghost is replayed in the world of the level, which must be set up.
+pub fn load_best_ghost(
+    state: &mut GameResources,
+    level: &Level,
+    difficulty: &Difficulty,
+    pkv: &PkvStore,
+) {
+    if state.ghosts.get(GhostKind::Best).is_some() {
+        return;
+    }
+    let Ok(saved) = pkv.get::<SavedGhost>(ghost_key(difficulty, state.current_level)) else {
+        return;
+    };
+    // The inputs would desync in a changed level, the next completed run replaces the ghost.
+    let thrust_history = match saved.inputs(level, difficulty) {
+        Ok(thrust_history) => thrust_history,
+        Err(e) => {
+            warn!("Discarding the saved ghost: {}", e);
+            return;
+        }
+    };
+    info!("Loading saved ghost");
+    let positions = compute_ghost(
+        &state.world,
+        initial_ship_center(level, &Screen::default()),
+        &thrust_history,
+    );
+    state.ghosts.set(Ghost {
+        kind: GhostKind::Best,
+        score: saved.score,
+        frame_count: saved.frame_count,
+        thrust_history,
+        positions,
+    });
+}
+
+/// Write the last run in the current directory, and log its share code which also works in the
+/// browser.
+fn export_replay(
+    mut events: EventReader<ExportReplay>,
+    state: Res<GameResources>,
+    difficulty: Res<Difficulty>,
+    levels: Res<Levels>,
+) {
+    for _ in events.read() {
+        let level = &levels.0[state.current_level];
+        // The TAS and practice runs are marked, so that they are not taken as a normal run.
+        let kind = if state.used_tas {
+            RunKind::Tas
+        } else if state.used_checkpoint {
+            RunKind::Practice
+        } else {
+            RunKind::Normal
+        };
+        let replay = crate::replay::Replay {
+            kind,
+            ..crate::replay::Replay::new(
+                level.hash(),
+                difficulty.key(),
+                state.thrust_history.clone(),
+            )
+        };
+        let path = format!(
+            "moonracer-{}-{}.{}",
+            state.current_level + 1,
+            state.frame_count,
+            crate::replay::EXTENSION
+        );
+        write_file(&path, &replay.encode());
+        info!("Replay share code: {}", replay.to_text());
+    }
+}
+
+/// The replay to import for a level, in the current directory. The file can be an exported
+/// replay or its share code.
+fn replay_path(level: usize) -> String {
+    format!("moonracer-{}.{}", level + 1, crate::replay::EXTENSION)
+}
+
+/// Check if the level has a replay to import.
+pub fn has_replay(level: usize) -> bool {
+    cfg!(not(target_arch = "wasm32")) && std::path::Path::new(&replay_path(level)).exists()
+}
+
+/// Play the imported replay in the current level, report the result to the player and race it.
+fn import_replay(
+    mut events: EventReader<ImportReplay>,
+    mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
+    levels: Res<Levels>,
+) {
+    for _ in events.read() {
+        let path = replay_path(state.current_level);
+        let Some(data) = read_bytes(&path) else {
+            state.import_status = Some(format!("Failed to read {}", path));
+            continue;
+        };
+        let level = &levels.0[state.current_level];
+        let replay = match crate::replay::load(&data, level.hash(), difficulty.key()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                error!("Invalid replay {}: {}", path, e);
+                state.import_status = Some(format!("Invalid replay: {}", e));
+                continue;
+            }
+        };
+        let initial = initial_ship_center(level, &Screen::default());
+        let ship = crate::physics::ShipState::new(initial);
+        let result = crate::level::play(
+            &state.world,
+            &state.objectives,
+            ship,
+            &replay.thrust_history,
+        );
+        let elapsed = result.frames as f32 * crate::resources::FREQ;
+        let status = match result.end {
+            Some(End::Completed) => {
+                format!("The replay completed the level in {:.03} sec", elapsed)
+            }
+            Some(End::Crashed(crash)) => {
+                format!("The replay crashed ({:?}) after {:.03} sec", crash, elapsed)
+            }
+            Some(End::HardLanding(reason)) => format!("The replay failed to land: {}", reason),
+            None => format!("The replay stopped after {:.03} sec", elapsed),
+        };
+        let status = match replay.kind {
+            RunKind::Normal => status,
+            kind => format!("{} ({} run)", status, kind.label()),
+        };
+        info!("Imported {}: {}", path, status);
+        state.import_status = Some(status);
+        let positions = compute_ghost(&state.world, initial, &replay.thrust_history);
+        // Like the script ghost, the imported ghost is raced but it is not saved.
+        state.ghosts.set(Ghost {
+            kind: GhostKind::Imported,
+            score: result.progress.score,
+            frame_count: result.frames,
+            thrust_history: replay.thrust_history,
+            positions,
+        });
+    }
+}
+
+/// The TAS script of a level, in the current directory.
+fn script_path(level: usize) -> String {
+    format!("moonracer-{}.tas", level + 1)
+}
+
+fn handle_script(
+    mut events: EventReader<Script>,
+    mut state: ResMut<GameResources>,
+    mut tas: ResMut<Tas>,
+    levels: Res<Levels>,
+    mut next_app_status: ResMut<NextState<AppStatus>>,
+    mut next_game_status: ResMut<NextState<GameStatus>>,
+) {
+    for event in events.read() {
+        let path = script_path(state.current_level);
+        if let Script::Save = event {
+            // Outside of the TAS mode, the inputs of the current run are saved.
+            let inputs = if tas.enabled {
+                &tas.inputs
+            } else {
+                &state.thrust_history
+            };
+            write_file(&path, crate::tas_script::serialize(inputs).as_bytes());
+            continue;
+        }
+        let Some(script) = read_file(&path) else {
+            continue;
+        };
+        let inputs = match crate::tas_script::parse(&script) {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                error!("Invalid script {}: {}", path, e);
+                continue;
+            }
+        };
+        info!("Loaded {} frames from {}", inputs.len(), path);
+        if let Script::LoadGhost = event {
+            let level = &levels.0[state.current_level];
+            let initial = initial_ship_center(level, &Screen::default());
+            let ship = crate::physics::ShipState::new(initial);
+            let result = crate::level::play(&state.world, &state.objectives, ship, &inputs);
+            let positions = compute_ghost(&state.world, initial, &inputs);
+            // The script ghost is raced, but it is not saved.
+            state.ghosts.set(Ghost {
+                kind: GhostKind::Script,
+                score: result.progress.score,
+                frame_count: result.frames,
+                thrust_history: inputs,
+                positions,
+            });
+        } else {
+            *tas = Tas {
+                enabled: true,
+                inputs,
+                ..default()
+            };
+            next_app_status.set(AppStatus::Tas);
+            next_game_status.set(GameStatus::Spawning);
+        }
+    }
+}
+
+/// Check if the level has a TAS script to load.
+pub fn has_script(level: usize) -> bool {
+    cfg!(not(target_arch = "wasm32")) && std::path::Path::new(&script_path(level)).exists()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_file(path: &str, data: &[u8]) {
+    match std::fs::write(path, data) {
+        Ok(()) => info!("Exported {}", path),
+        Err(e) => error!("Failed to export {}: {}", path, e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_file(path: &str, _data: &[u8]) {
+    warn!("Exporting {} is not supported in the browser", path);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_bytes(path: &str) -> Option<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| error!("Failed to read {}: {}", path, e))
+        .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_bytes(path: &str) -> Option<Vec<u8>> {
+    warn!("Reading {} is not supported in the browser", path);
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_file(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| error!("Failed to read {}: {}", path, e))
+        .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_file(path: &str) -> Option<String> {
+    warn!("Reading {} is not supported in the browser", path);
+    None
+}
+
+fn save_difficulty(
+    mut state: ResMut<GameResources>,
+    difficulty: Res<Difficulty>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    info!("Using difficulty {:?}", *difficulty);
+    pkv.set("difficulty", &difficulty.key())
+        .expect("failed to store difficulty");
+    // Each difficulty has its own highscores.
+    state.highscores = pkv.get(difficulty.key()).unwrap_or_default();
+    state.fuel_records = pkv.get(fuel_key(&difficulty)).unwrap_or_default();
+    state.tas_records = pkv.get(tas_key(&difficulty)).unwrap_or_default();
+    // The ghosts depend on the physics, they are reloaded when the level spawns.
+    state.ghosts.clear();
+}
+
+fn save_stick_settings(stick_settings: Res<StickSettings>, mut pkv: ResMut<PkvStore>) {
+    pkv.set("stick", &*stick_settings)
+        .expect("failed to store stick settings");
+}
+
+fn setup(
+    mut state: ResMut<GameResources>,
+    mut difficulty: ResMut<Difficulty>,
+    mut stick_settings: ResMut<StickSettings>,
+    levels: Res<Levels>,
+    pkv: ResMut<PkvStore>,
+) {
+    if let Some(saved) = pkv
+        .get::<String>("difficulty")
+        .ok()
+        .and_then(|key| Difficulty::from_key(&key))
+    {
+        *difficulty = saved;
+    }
+    if let Ok(saved) = pkv.get::<StickSettings>("stick") {
+        *stick_settings = saved;
+    }
+    if let Ok(times) = pkv.get::<bevy::utils::HashMap<usize, usize>>(difficulty.key()) {
         info!("Loading saved data");
         // restore current_level to the last highscore.
         state.current_level = times.len().min(levels.0.len() - 1);
@@ -41,4 +455,33 @@ fn setup(mut state: ResMut<GameResources>, levels: Res<Levels>, pkv: ResMut<PkvS
     } else {
         info!("New save data");
     }
+    state.fuel_records = pkv.get(fuel_key(&difficulty)).unwrap_or_default();
+    state.tas_records = pkv.get(tas_key(&difficulty)).unwrap_or_default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_saved_ghost_is_discarded_when_the_level_changes() {
+        let mut level = crate::level_loader::load().0.remove(0);
+        let ghost = Ghost {
+            kind: GhostKind::Best,
+            score: 0,
+            frame_count: 2,
+            thrust_history: vec![Vec2::Y, Vec2::ZERO],
+            positions: vec![],
+        };
+        let saved = SavedGhost::new(&ghost, &level, &Difficulty::default());
+        assert_eq!(
+            saved.inputs(&level, &Difficulty::default()),
+            Ok(ghost.thrust_history)
+        );
+        level.walls[0].size.x += 0.01;
+        assert!(matches!(
+            saved.inputs(&level, &Difficulty::default()),
+            Err(ReplayError::LevelMismatch { .. })
+        ));
+    }
 }
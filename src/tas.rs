@@ -0,0 +1,225 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module implements the tool assisted speedrun (TAS) mode.
+//!
+//! In this mode the simulation only advances when the player steps a frame. The inputs are kept
+//! in a timeline, so that any frame can be edited, and the player can jump to any frame by
+//! re-simulating the inputs from the start of the level.
+
+use bevy::prelude::*;
+
+use crate::app_status::AppStatus;
+use crate::entities::{goal, launch_pad, ship};
+use crate::game_status::GameStatus;
+use crate::level;
+use crate::physics;
+use crate::resources::{GameResources, Tas};
+
+pub struct Plug;
+impl Plugin for Plug {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tas>()
+            .add_systems(OnEnter(AppStatus::Tas), crate::ui::timeline::spawn)
+            .add_systems(OnExit(AppStatus::Tas), crate::ui::timeline::despawn)
+            .add_systems(
+                Update,
+                (
+                    handle_input.after(crate::game_status::handle_input),
+                    crate::ui::timeline::update,
+                )
+                    .chain()
+                    .run_if(in_state(AppStatus::Tas)),
+            );
+    }
+}
+
+/// The fixed update ticks only when a frame is stepped in TAS mode.
+pub fn can_step(app_status: Res<State<AppStatus>>, tas: Res<Tas>) -> bool {
+    *app_status.get() != AppStatus::Tas || tas.pending > 0
+}
+
+/// Use the timeline input for the stepped frame.
+pub fn apply_step(
+    app_status: Res<State<AppStatus>>,
+    mut state: ResMut<GameResources>,
+    mut tas: ResMut<Tas>,
+) {
+    if *app_status.get() != AppStatus::Tas {
+        return;
+    }
+    state.thrust = tas
+        .inputs
+        .get(state.frame_count)
+        .copied()
+        .unwrap_or_default();
+    state.used_tas = true;
+    tas.pending -= 1;
+}
+
+/// Re-simulate the inputs from the start of the level, stopping before the frame that ends the
+/// run, so that the player steps it to crash or to complete the level.
+pub fn rewind(
+    world: &physics::World,
+    objectives: &level::Objectives,
+    initial: Vec2,
+    inputs: &[Vec2],
+) -> level::Run {
+    let ship = physics::ShipState::new(initial);
+    let run = level::play(world, objectives, ship, inputs);
+    match run.end {
+        Some(_) => level::play(world, objectives, ship, &inputs[..run.frames - 1]),
+        None => run,
+    }
+}
+
+// evdev
+const N: ScanCode = ScanCode(49);
+const B: ScanCode = ScanCode(48);
+const J: ScanCode = ScanCode(36);
+const E: ScanCode = ScanCode(18);
+const LB: ScanCode = ScanCode(26);
+const RB: ScanCode = ScanCode(27);
+
+// wasm (firefox)
+const N_W: ScanCode = ScanCode(78);
+const B_W: ScanCode = ScanCode(66);
+const J_W: ScanCode = ScanCode(74);
+const E_W: ScanCode = ScanCode(69);
+const LB_W: ScanCode = ScanCode(219);
+const RB_W: ScanCode = ScanCode(221);
+
+#[allow(clippy::too_many_arguments)]
+fn handle_input(
+    keyboard_input: Res<Input<ScanCode>>,
+    mut state: ResMut<GameResources>,
+    mut tas: ResMut<Tas>,
+    game_status: Res<State<GameStatus>>,
+    mut next_game_status: ResMut<NextState<GameStatus>>,
+    levels: Res<level::Levels>,
+    pad_materials: Res<launch_pad::PadMaterials>,
+    mut query: ParamSet<(
+        Query<(&mut Transform, &mut ship::Velocity), With<ship::Ship>>,
+        Query<&mut Transform, With<goal::Goal>>,
+    )>,
+    mut pad_query: Query<&mut Handle<StandardMaterial>, With<launch_pad::Pad>>,
+) {
+    let pressed = |keys: [ScanCode; 2]| keyboard_input.get_just_pressed().any(|k| keys.contains(k));
+    let flying = *game_status.get() == GameStatus::Flying;
+
+    if pressed([LB, LB_W]) {
+        tas.selected = tas.selected.saturating_sub(1);
+    }
+    if pressed([RB, RB_W]) {
+        tas.selected = (tas.selected + 1).min(tas.inputs.len());
+    }
+
+    if pressed([N, N_W]) {
+        // The held direction replaces the recorded input.
+        let frame = state.frame_count + tas.pending;
+        let thrust = match state.thrust {
+            Vec2::ZERO => tas.inputs.get(frame).copied().unwrap_or_default(),
+            thrust => thrust,
+        };
+        // The ship waits on the launch pad until the engine is fired.
+        if thrust != Vec2::ZERO || flying {
+            match tas.inputs.get_mut(frame) {
+                Some(input) => *input = thrust,
+                None => tas.inputs.push(thrust),
+            }
+            tas.pending += 1;
+            tas.selected = frame + 1;
+            next_game_status.set(GameStatus::Flying);
+        }
+        return;
+    }
+
+    // The other commands re-simulate the run, wait for the stepped frames to be done.
+    if tas.pending > 0 || state.destroyed {
+        return;
+    }
+    let target = if pressed([B, B_W]) {
+        state.frame_count.saturating_sub(1)
+    } else if pressed([J, J_W]) {
+        tas.selected
+    } else if pressed([E, E_W]) {
+        let (selected, thrust) = (tas.selected, state.thrust);
+        match tas.inputs.get_mut(selected) {
+            Some(input) => *input = thrust,
+            None => tas.inputs.push(thrust),
+        }
+        // Editing a past frame changes the current position.
+        state.frame_count
+    } else {
+        return;
+    };
+
+    let level = &levels.0[state.current_level];
+    let initial = level::initial_ship_center(level, &level::Screen::default());
+    let target = target.min(tas.inputs.len());
+    let result = rewind(
+        &state.world,
+        &state.objectives,
+        initial,
+        &tas.inputs[..target],
+    );
+    info!("Jumped to frame {}", result.frames);
+    state.ship = result.ship;
+    state.progress = result.progress;
+    state.frame_count = result.frames;
+    state.thrust_history = tas.inputs[..result.frames].to_vec();
+    state.contacts.clear();
+    state.used_tas = true;
+    next_game_status.set(if result.frames > 0 {
+        GameStatus::Flying
+    } else {
+        GameStatus::Idling
+    });
+
+    let mut ship_query = query.p0();
+    let (mut transform, mut velocity) = ship_query.single_mut();
+    transform.translation = state.ship.pos.extend(0.0);
+    transform.rotation = Quat::from_rotation_z(state.ship.heading);
+    *velocity = ship::Velocity(state.ship.velocity);
+
+    let next_goal = state.objectives.goals.get(state.progress.score).copied();
+    query.p1().single_mut().translation = next_goal.unwrap_or(level::OFFSCREEN).extend(0.0);
+    *pad_query.single_mut() = match next_goal {
+        Some(_) => pad_materials.idle.clone(),
+        None => pad_materials.active.clone(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{PhysicsParams, Wall, World};
+
+    #[test]
+    fn rewind_counts_the_goals_and_stops_before_the_crash() {
+        let world = World::new(
+            vec![Wall::trap(Vec2::new(0.0, 1.0), Vec2::new(2.0, 0.1))],
+            PhysicsParams {
+                gravity: Vec2::ZERO,
+                damping: 1.0,
+                thrust_power: Vec2::splat(0.001),
+                ..Default::default()
+            },
+        );
+        let objectives = level::Objectives {
+            goals: vec![Vec2::new(0.0, 0.1), Vec2::new(0.0, 0.3)],
+            ..Default::default()
+        };
+        let inputs = vec![Vec2::Y; 120];
+        let result = rewind(&world, &objectives, Vec2::ZERO, &inputs);
+        assert_eq!(result.progress.score, 2);
+        assert_eq!(result.end, None);
+        assert!(result.frames < inputs.len());
+        // The rewind gives the same state as the simulation of the same frames.
+        let states = world.simulate(
+            physics::ShipState::new(Vec2::ZERO),
+            &inputs[..result.frames],
+        );
+        assert_eq!(Some(&result.ship), states.last());
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module implements the text format of the TAS inputs.
+//!
+//! Each line holds a thrust for a number of frames, for example:
+//!
+//! ```text
+//! # lift off, then go right
+//! 10 U
+//! 25 UR
+//! 40 .
+//! 3 0.25 -1
+//! ```
+//!
+//! The direction is made of the U, D, L and R letters, or `.` to release the engine.
+//! A stick thrust is written with its x and y values. Empty lines and `#` comments are ignored.
+//! The script is limited to [MAX_FRAMES] frames.
+
+use std::fmt;
+
+use bevy::math::Vec2;
+
+use crate::thrust_codec::MAX_FRAMES;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Read the thrust history of a script.
+pub fn parse(script: &str) -> Result<Vec<Vec2>, ScriptError> {
+    let mut thrust_history = vec![];
+    for (idx, line) in script.lines().enumerate() {
+        let error = |message: String| ScriptError {
+            line: idx + 1,
+            message,
+        };
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(count) = words.next() else {
+            continue;
+        };
+        let count: usize = count
+            .parse()
+            .map_err(|_| error(format!("invalid frame count '{}'", count)))?;
+        let thrust = match (words.next(), words.next(), words.next()) {
+            (Some(direction), None, None) => parse_direction(direction)
+                .ok_or_else(|| error(format!("invalid direction '{}'", direction)))?,
+            (Some(x), Some(y), None) => {
+                let value = |value: &str| {
+                    value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|v| (-1.0..=1.0).contains(v))
+                        .ok_or_else(|| error(format!("invalid thrust '{}'", value)))
+                };
+                Vec2::new(value(x)?, value(y)?)
+            }
+            (None, _, _) => return Err(error("missing direction".into())),
+            _ => return Err(error(format!("unexpected input '{}'", line.trim()))),
+        };
+        // The script is limited like the replays, before allocating the frames.
+        let len = thrust_history
+            .len()
+            .checked_add(count)
+            .filter(|len| *len <= MAX_FRAMES)
+            .ok_or_else(|| error(format!("the script exceeds {} frames", MAX_FRAMES)))?;
+        thrust_history.resize(len, thrust);
+    }
+    Ok(thrust_history)
+}
+
+/// Write the thrust history, grouping the consecutive frames with the same thrust.
+pub fn serialize(thrust_history: &[Vec2]) -> String {
+    let mut script = String::new();
+    let mut frames = thrust_history.iter().peekable();
+    while let Some(thrust) = frames.next() {
+        let mut count = 1;
+        while frames.next_if(|next| same(next, thrust)).is_some() {
+            count += 1;
+        }
+        let thrust = match direction(*thrust) {
+            Some(direction) => direction,
+            // The f32 Display gives the shortest text that is parsed back to the same value.
+            None => format!("{} {}", thrust.x, thrust.y),
+        };
+        script.push_str(&format!("{} {}\n", count, thrust));
+    }
+    script
+}
+
+fn parse_direction(direction: &str) -> Option<Vec2> {
+    if direction == "." {
+        return Some(Vec2::ZERO);
+    }
+    let mut thrust = Vec2::ZERO;
+    for letter in direction.chars() {
+        let (axis, value) = match letter.to_ascii_uppercase() {
+            'U' => (&mut thrust.y, 1.0),
+            'D' => (&mut thrust.y, -1.0),
+            'L' => (&mut thrust.x, -1.0),
+            'R' => (&mut thrust.x, 1.0),
+            _ => return None,
+        };
+        // Each axis is set once, e.g. "UD" is invalid.
+        if *axis != 0.0 {
+            return None;
+        }
+        *axis = value;
+    }
+    Some(thrust)
+}
+
+/// The letters of a keyboard thrust, comparing the bits so that -0.0 is written as a number.
+fn direction(thrust: Vec2) -> Option<String> {
+    let letter = |value: f32, neg: char, pos: char| match value.to_bits() {
+        bits if bits == 0.0f32.to_bits() => Some(None),
+        bits if bits == (-1.0f32).to_bits() => Some(Some(neg)),
+        bits if bits == 1.0f32.to_bits() => Some(Some(pos)),
+        _ => None,
+    };
+    match (letter(thrust.y, 'D', 'U')?, letter(thrust.x, 'L', 'R')?) {
+        (None, None) => Some(".".into()),
+        (y, x) => Some(y.into_iter().chain(x).collect()),
+    }
+}
+
+fn same(a: &Vec2, b: &Vec2) -> bool {
+    a.x.to_bits() == b.x.to_bits() && a.y.to_bits() == b.y.to_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_is_parsed() {
+        let thrust_history = parse("# example\n2 R\n\n1 UR  # climb\n3 .\n1 0.5 -1\n").unwrap();
+        assert_eq!(
+            thrust_history,
+            vec![
+                Vec2::X,
+                Vec2::X,
+                Vec2::ONE,
+                Vec2::ZERO,
+                Vec2::ZERO,
+                Vec2::ZERO,
+                Vec2::new(0.5, -1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn script_round_trips() {
+        let thrust_history = vec![
+            Vec2::ZERO,
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(0.1, -0.7),
+            Vec2::new(-0.0, 1.0),
+            Vec2::NEG_Y,
+        ];
+        let script = serialize(&thrust_history);
+        assert_eq!(script, "1 .\n2 UL\n1 0.1 -0.7\n1 -0 1\n1 D\n");
+        let parsed = parse(&script).unwrap();
+        assert!(parsed.iter().zip(&thrust_history).all(|(a, b)| same(a, b)));
+        assert_eq!(parsed.len(), thrust_history.len());
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        let error = |script| parse(script).unwrap_err();
+        assert_eq!(error("1 U\nten R").line, 2);
+        assert_eq!(
+            error("1 U\n\n3 UD").to_string(),
+            "line 3: invalid direction 'UD'"
+        );
+        assert_eq!(error("4").message, "missing direction");
+        assert_eq!(error("1 2.0 0").message, "invalid thrust '2.0'");
+        assert_eq!(error("1 U R").line, 1);
+    }
+
+    #[test]
+    fn long_scripts_are_rejected() {
+        let error = |script: String| parse(&script).unwrap_err();
+        let too_long = format!("the script exceeds {} frames", MAX_FRAMES);
+        let overflow = error(format!("1 U\n{} R", usize::MAX));
+        assert_eq!((overflow.line, overflow.message), (2, too_long.clone()));
+        let over_cap = error(format!("# too long\n{} .", MAX_FRAMES + 1));
+        assert_eq!((over_cap.line, over_cap.message), (2, too_long));
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module implements the compact encoding of the thrust history.
+//!
+//! The inputs are runs of the same thrust, thus each run is stored once with its length:
+//!
+//! - the high 4 bits of the first byte are the direction, the index of the x and y values
+//!   in [-1, 0, 1] as `x + 3 * y`, or 15 for an analog thrust,
+//! - the low 3 bits hold the run length minus one, and the bit 3 is set when the length
+//!   continues in the next bytes, as a little-endian base 128 varint,
+//! - an analog thrust is followed by its x and y values as little-endian f32, which must be
+//!   in [-1, 1] like the stick inputs.
+//!
+//! A run of up to 8 keyboard frames takes a single byte. The text format is the url safe
+//! base64 of the bytes, so that a run can be shared as a string.
+
+use std::fmt;
+
+use base64::Engine;
+use bevy::math::Vec2;
+
+/// The direction of a thrust that is not digital.
+const ANALOG: u8 = 15;
+
+const DIGITAL: [f32; 3] = [-1.0, 0.0, 1.0];
+
+const MORE: u8 = 0b1000;
+
+/// The longest history that is decoded, 10 minutes is far longer than any level run.
+/// The limit is checked before the frames are allocated, so that a bad input is rejected quickly.
+pub const MAX_FRAMES: usize = 10 * 60 * crate::physics::TICK_RATE as usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecError {
+    Truncated,
+    InvalidDirection(u8),
+    InvalidThrust(Vec2),
+    TooLong,
+    InvalidText,
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::Truncated => write!(f, "the inputs are truncated"),
+            CodecError::InvalidDirection(direction) => {
+                write!(f, "invalid direction {}", direction)
+            }
+            CodecError::InvalidThrust(thrust) => {
+                write!(f, "invalid thrust {} {}", thrust.x, thrust.y)
+            }
+            CodecError::TooLong => write!(f, "the inputs exceed {} frames", MAX_FRAMES),
+            CodecError::InvalidText => write!(f, "the inputs are not valid base64"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+pub fn encode(thrust_history: &[Vec2]) -> Vec<u8> {
+    let mut data = vec![];
+    let mut frames = thrust_history.iter().peekable();
+    while let Some(thrust) = frames.next() {
+        let mut len: usize = 1;
+        while frames.next_if(|next| same(next, thrust)).is_some() {
+            len += 1;
+        }
+        let direction = direction(*thrust).unwrap_or(ANALOG);
+        push_run(&mut data, direction, len);
+        if direction == ANALOG {
+            data.extend_from_slice(&thrust.x.to_le_bytes());
+            data.extend_from_slice(&thrust.y.to_le_bytes());
+        }
+    }
+    data
+}
+
+fn push_run(data: &mut Vec<u8>, direction: u8, len: usize) {
+    let mut rest = len - 1;
+    let more = if rest > 7 { MORE } else { 0 };
+    data.push(direction << 4 | more | (rest & 7) as u8);
+    rest >>= 3;
+    while rest > 0 {
+        let more = if rest > 127 { 0x80 } else { 0 };
+        data.push(more | (rest & 127) as u8);
+        rest >>= 7;
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<Vec<Vec2>, CodecError> {
+    let mut thrust_history = vec![];
+    let mut bytes = data.iter().copied();
+    let mut next = || bytes.next().ok_or(CodecError::Truncated);
+    while let Ok(head) = next() {
+        let mut len = (head & 7) as usize;
+        if head & MORE != 0 {
+            let mut shift = 3;
+            loop {
+                let byte = next()?;
+                len |= ((byte & 127) as usize) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+                // The padding bytes of a varint are not accepted either.
+                if len >= MAX_FRAMES || shift > 24 {
+                    return Err(CodecError::TooLong);
+                }
+            }
+        }
+        let len = len + 1;
+        let thrust = match head >> 4 {
+            ANALOG => {
+                let mut value = || -> Result<f32, CodecError> {
+                    Ok(f32::from_le_bytes([next()?, next()?, next()?, next()?]))
+                };
+                let thrust = Vec2::new(value()?, value()?);
+                // A forged input could push the ship harder than the stick, or be NaN.
+                if !thrust.to_array().iter().all(|v| (-1.0..=1.0).contains(v)) {
+                    return Err(CodecError::InvalidThrust(thrust));
+                }
+                thrust
+            }
+            direction => thrust(direction).ok_or(CodecError::InvalidDirection(direction))?,
+        };
+        // The running total is checked before growing the history.
+        if len > MAX_FRAMES - thrust_history.len() {
+            return Err(CodecError::TooLong);
+        }
+        thrust_history.resize(thrust_history.len() + len, thrust);
+    }
+    Ok(thrust_history)
+}
+
+/// Encode the thrust history as a shareable string.
+pub fn to_text(thrust_history: &[Vec2]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(encode(thrust_history))
+}
+
+pub fn from_text(text: &str) -> Result<Vec<Vec2>, CodecError> {
+    let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(text.trim())
+        .map_err(|_| CodecError::InvalidText)?;
+    decode(&data)
+}
+
+/// The direction of a keyboard thrust, comparing the bits so that -0.0 is replayed exactly.
+pub fn direction(thrust: Vec2) -> Option<u8> {
+    let index = |value: f32| {
+        DIGITAL
+            .iter()
+            .position(|digital| digital.to_bits() == value.to_bits())
+            .map(|idx| idx as u8)
+    };
+    Some(index(thrust.x)? + 3 * index(thrust.y)?)
+}
+
+pub fn thrust(direction: u8) -> Option<Vec2> {
+    (direction < 9).then(|| {
+        Vec2::new(
+            DIGITAL[(direction % 3) as usize],
+            DIGITAL[(direction / 3) as usize],
+        )
+    })
+}
+
+fn same(a: &Vec2, b: &Vec2) -> bool {
+    a.x.to_bits() == b.x.to_bits() && a.y.to_bits() == b.y.to_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Mostly keyboard inputs held for a while, with some stick values, including -0.0.
+    fn thrust_strategy() -> impl Strategy<Value = (Vec2, usize)> {
+        let value = prop_oneof![
+            4 => prop::sample::select(vec![-1.0, 0.0, 1.0, -0.0]),
+            1 => -1.0f32..=1.0,
+        ];
+        (
+            (value.clone(), value).prop_map(|(x, y)| Vec2::new(x, y)),
+            1..300usize,
+        )
+    }
+
+    fn bits(thrust_history: &[Vec2]) -> Vec<(u32, u32)> {
+        thrust_history
+            .iter()
+            .map(|t| (t.x.to_bits(), t.y.to_bits()))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn inputs_round_trip(runs in prop::collection::vec(thrust_strategy(), 0..50)) {
+            let thrust_history: Vec<Vec2> = runs
+                .into_iter()
+                .flat_map(|(thrust, len)| std::iter::repeat(thrust).take(len))
+                .collect();
+            let data = encode(&thrust_history);
+            prop_assert!(data.len() <= thrust_history.len() * 9);
+            prop_assert_eq!(bits(&decode(&data).unwrap()), bits(&thrust_history));
+            let text = to_text(&thrust_history);
+            prop_assert_eq!(bits(&from_text(&text).unwrap()), bits(&thrust_history));
+        }
+
+        #[test]
+        fn long_inputs_are_rejected(lens in prop::collection::vec(1..=2 * MAX_FRAMES, 1..20)) {
+            let mut data = vec![];
+            for (idx, len) in lens.iter().enumerate() {
+                // Alternate the directions, so that the runs are not merged.
+                push_run(&mut data, (idx % 2) as u8, *len);
+            }
+            let total: usize = lens.iter().sum();
+            match decode(&data) {
+                Ok(thrust_history) => prop_assert_eq!(thrust_history.len(), total),
+                Err(e) => {
+                    prop_assert_eq!(e, CodecError::TooLong);
+                    prop_assert!(total > MAX_FRAMES);
+                }
+            }
+        }
+
+        #[test]
+        fn bad_data_is_rejected_without_panic(data in prop::collection::vec(any::<u8>(), 0..64)) {
+            if let Ok(thrust_history) = decode(&data) {
+                prop_assert!(thrust_history.len() <= MAX_FRAMES);
+            }
+        }
+    }
+
+    #[test]
+    fn runs_are_compact() {
+        let mut thrust_history = vec![Vec2::Y; 8];
+        assert_eq!(encode(&thrust_history), vec![7 << 4 | 7]);
+        thrust_history.push(Vec2::Y);
+        assert_eq!(encode(&thrust_history), vec![7 << 4 | MORE, 1]);
+        thrust_history.push(Vec2::new(0.5, -0.0));
+        assert_eq!(encode(&thrust_history).len(), 2 + 9);
+        // Up to 1024 frames take two bytes, a minute of holding a direction takes three.
+        assert_eq!(encode(&[Vec2::X; 1024]).len(), 2);
+        assert_eq!(encode(&[Vec2::X; 3600]).len(), 3);
+        assert_eq!(
+            decode(&encode(&[Vec2::X; MAX_FRAMES])).unwrap().len(),
+            MAX_FRAMES
+        );
+    }
+
+    #[test]
+    fn bad_data_is_rejected() {
+        assert_eq!(decode(&[9 << 4]), Err(CodecError::InvalidDirection(9)));
+        assert_eq!(decode(&[MORE]), Err(CodecError::Truncated));
+        assert_eq!(decode(&[ANALOG << 4, 0, 0]), Err(CodecError::Truncated));
+        assert_eq!(
+            decode(&[MORE, 0xff, 0xff, 0xff, 0x7f]),
+            Err(CodecError::TooLong)
+        );
+        let mut data = encode(&[Vec2::X; MAX_FRAMES]);
+        data.push(0);
+        assert_eq!(decode(&data), Err(CodecError::TooLong));
+        assert_eq!(from_text("not base64!"), Err(CodecError::InvalidText));
+        let analog = |x: f32, y: f32| {
+            let mut data = vec![ANALOG << 4];
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+            decode(&data)
+        };
+        assert_eq!(analog(1.0, -0.5), Ok(vec![Vec2::new(1.0, -0.5)]));
+        assert_eq!(
+            analog(100.0, 100.0),
+            Err(CodecError::InvalidThrust(Vec2::new(100.0, 100.0)))
+        );
+        assert!(matches!(
+            analog(f32::NAN, 0.0),
+            Err(CodecError::InvalidThrust(_))
+        ));
+        assert!(analog(0.0, f32::INFINITY).is_err());
+    }
+}
@@ -37,6 +37,24 @@ pub fn spawn(mut commands: Commands, state: Res<GameResources>, levels: Res<Leve
                     ..default()
                 })
                 .with_children(|parent| {
+                    if state.used_tas {
+                        parent.spawn(TextBundle::from_section(
+                            "TAS run, saved in the TAS records",
+                            STYLE_TEXT.clone(),
+                        ));
+                    }
+                    if state.made_tas_record {
+                        parent.spawn(TextBundle::from_section(
+                            "New TAS record!",
+                            STYLE_TEXT.clone(),
+                        ));
+                    }
+                    if state.used_checkpoint {
+                        parent.spawn(TextBundle::from_section(
+                            "Practice run, not eligible for highscores",
+                            STYLE_TEXT.clone(),
+                        ));
+                    }
                     if state.made_highscore {
                         parent.spawn(TextBundle::from_section(
                             "New HighScore!",
@@ -47,6 +65,24 @@ pub fn spawn(mut commands: Commands, state: Res<GameResources>, levels: Res<Leve
                         format!("Final Score: {}", state.elapsed()),
                         STYLE_TEXT.clone(),
                     ));
+                    if let Some(fuel) = state.ship.fuel_left(&state.world.params) {
+                        if state.made_fuel_record {
+                            parent.spawn(TextBundle::from_section(
+                                "New fuel record!",
+                                STYLE_TEXT.clone(),
+                            ));
+                        }
+                        parent.spawn(TextBundle::from_section(
+                            format!("Fuel left: {}", fuel),
+                            STYLE_TEXT.clone(),
+                        ));
+                    }
+                    if state.failed_attempts > 0 {
+                        parent.spawn(TextBundle::from_section(
+                            format!("Failed attempts: {}", state.failed_attempts),
+                            STYLE_TEXT.clone(),
+                        ));
+                    }
                     let has_remaining_level = state.current_level + 1 < levels.0.len();
                     parent
                         .spawn(NodeBundle {
@@ -65,6 +101,11 @@ pub fn spawn(mut commands: Commands, state: Res<GameResources>, levels: Res<Leve
                                 );
                             }
                             crate::ui::button::spawn_button(parent, "Restart", MenuAction::Restart);
+                            crate::ui::button::spawn_button(
+                                parent,
+                                "Export Replay",
+                                MenuAction::ExportReplay,
+                            );
                             if !has_remaining_level {
                                 crate::ui::button::spawn_button(
                                     parent,
@@ -0,0 +1,56 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the fuel gauge displayed when the level has a limited fuel.
+
+use bevy::prelude::*;
+
+use crate::level::LevelComponent;
+use crate::resources::GameResources;
+
+#[derive(Component)]
+pub struct FuelGauge;
+
+pub fn spawn(commands: &mut Commands, capacity: usize) {
+    info!("Fuel capacity: {}", capacity);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    width: Val::Px(200.0),
+                    height: Val::Px(16.0),
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                border_color: Color::WHITE.into(),
+                ..default()
+            },
+            LevelComponent,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.9, 0.6, 0.0).into(),
+                    ..default()
+                },
+                FuelGauge,
+            ));
+        });
+}
+
+pub fn update(state: Res<GameResources>, mut query: Query<&mut Style, With<FuelGauge>>) {
+    let params = &state.world.params;
+    if let (Some(capacity), Some(left)) = (params.fuel, state.ship.fuel_left(params)) {
+        for mut style in query.iter_mut() {
+            style.width = Val::Percent(100.0 * left as f32 / capacity.max(1) as f32);
+        }
+    }
+}
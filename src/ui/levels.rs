@@ -78,6 +78,18 @@ pub fn spawn(
                             None => "         ".into(),
                         };
                         parent.spawn(TextBundle::from_section(&score, STYLE_TEXT.clone()));
+                        if let Some(record) = state.tas_records.get(&pos) {
+                            parent.spawn(TextBundle::from_section(
+                                format!(" TAS {:.03}", *record as f32 * FREQ),
+                                STYLE_TEXT.clone(),
+                            ));
+                        }
+                        if let Some(fuel) = state.fuel_records.get(&pos) {
+                            parent.spawn(TextBundle::from_section(
+                                format!(" {} fuel", fuel),
+                                STYLE_TEXT.clone(),
+                            ));
+                        }
                     });
             }
             let total_score: usize = state.highscores.values().sum();
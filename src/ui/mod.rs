@@ -4,5 +4,8 @@
 pub mod background;
 pub mod button;
 pub mod completed;
+pub mod fuel;
 pub mod levels;
 pub mod pause;
+pub mod settings;
+pub mod timeline;
@@ -4,10 +4,17 @@
 use bevy::prelude::*;
 
 use crate::app_status::{AppStatus, MenuAction, MenuElem};
+use crate::events::Script;
+use crate::resources::{GameResources, GhostKind, Tas};
 
 use super::button::STYLE_TEXT;
 
-pub fn spawn(mut commands: Commands) {
+/// The text of the replay import result.
+#[derive(Component)]
+pub struct ImportStatus;
+
+pub fn spawn(mut commands: Commands, mut state: ResMut<GameResources>, tas: Res<Tas>) {
+    state.import_status = None;
     commands
         .spawn((
             NodeBundle {
@@ -37,14 +44,124 @@ pub fn spawn(mut commands: Commands) {
                     crate::ui::button::spawn_button(
                         parent,
                         "Resume",
-                        MenuAction::SelectMenu(AppStatus::Playing),
+                        MenuAction::SelectMenu(tas.playing_status()),
                     );
                     crate::ui::button::spawn_button(parent, "Restart", MenuAction::Restart);
+                    if state.checkpoint.is_some() && !tas.enabled {
+                        crate::ui::button::spawn_button(
+                            parent,
+                            "Checkpoint",
+                            MenuAction::RespawnCheckpoint,
+                        );
+                    }
+                    let tas_label = if tas.enabled { "Exit TAS" } else { "TAS Mode" };
+                    crate::ui::button::spawn_button(parent, tas_label, MenuAction::ToggleTas);
                     crate::ui::button::spawn_button(
                         parent,
                         "Quit",
                         MenuAction::SelectMenu(AppStatus::Menu),
                     );
                 });
+            // The TAS script of the level, see the tas_script module.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    crate::ui::button::spawn_button(
+                        parent,
+                        "Save Script",
+                        MenuAction::Script(Script::Save),
+                    );
+                    if crate::resources::save::has_script(state.current_level) {
+                        crate::ui::button::spawn_button(
+                            parent,
+                            "Script Ghost",
+                            MenuAction::Script(Script::LoadGhost),
+                        );
+                        crate::ui::button::spawn_button(
+                            parent,
+                            "Script Inputs",
+                            MenuAction::Script(Script::LoadInputs),
+                        );
+                    }
+                    if crate::resources::save::has_replay(state.current_level) {
+                        crate::ui::button::spawn_button(
+                            parent,
+                            "Import Replay",
+                            MenuAction::ImportReplay,
+                        );
+                    }
+                });
+            parent.spawn((
+                TextBundle::from_section("", STYLE_TEXT.clone()),
+                ImportStatus,
+            ));
+            // The ghosts of the level, they can be hidden to focus on one of them.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for kind in GhostKind::ALL {
+                        if state.ghosts.get(kind).is_some() {
+                            crate::ui::button::spawn_button(
+                                parent,
+                                &ghost_toggle_label(&state, kind),
+                                MenuAction::ToggleGhost(kind),
+                            );
+                        }
+                    }
+                });
         });
 }
+
+fn ghost_toggle_label(state: &GameResources, kind: GhostKind) -> String {
+    let action = if state.ghosts.is_visible(kind) {
+        "Hide"
+    } else {
+        "Show"
+    };
+    format!("{} {}", action, kind.label())
+}
+
+/// Update the labels of the ghost buttons when they are toggled.
+pub fn update_ghost_toggles(
+    state: Res<GameResources>,
+    buttons: Query<(&MenuAction, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for (action, children) in &buttons {
+        let MenuAction::ToggleGhost(kind) = action else {
+            continue;
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = ghost_toggle_label(&state, *kind);
+        }
+    }
+}
+
+/// Display the result of the replay import, or why it failed.
+pub fn update_import_status(
+    state: Res<GameResources>,
+    mut texts: Query<&mut Text, With<ImportStatus>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = state.import_status.clone().unwrap_or_default();
+    }
+}
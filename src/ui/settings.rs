@@ -0,0 +1,93 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+use bevy::prelude::*;
+
+use crate::app_status::{MenuAction, MenuElem};
+use crate::resources::{Difficulty, StickSettings};
+
+use super::button::STYLE_TEXT;
+
+pub fn spawn(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    stick_settings: Res<StickSettings>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            MenuElem,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Select a difficulty",
+                STYLE_TEXT.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Current: {}", difficulty.name()),
+                STYLE_TEXT.clone(),
+            ));
+            for difficulty in Difficulty::ALL {
+                crate::ui::button::spawn_button(
+                    parent,
+                    difficulty.name(),
+                    MenuAction::SetDifficulty(difficulty),
+                );
+            }
+            // The proportional thrust of the gamepad left stick.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in [MenuAction::NextStickDeadzone, MenuAction::NextStickCurve] {
+                        let label = stick_label(&stick_settings, &action).unwrap_or_default();
+                        crate::ui::button::spawn_button(parent, &label, action);
+                    }
+                });
+        });
+}
+
+fn stick_label(stick_settings: &StickSettings, action: &MenuAction) -> Option<String> {
+    match action {
+        MenuAction::NextStickDeadzone => {
+            Some(format!("Deadzone {:.0}%", stick_settings.deadzone * 100.0))
+        }
+        MenuAction::NextStickCurve => Some(format!("Curve x{:.1}", stick_settings.exponent)),
+        _ => None,
+    }
+}
+
+/// Update the labels of the stick buttons when the settings change.
+pub fn update_stick_labels(
+    stick_settings: Res<StickSettings>,
+    buttons: Query<(&MenuAction, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !stick_settings.is_changed() {
+        return;
+    }
+    for (action, children) in &buttons {
+        let Some(label) = stick_label(&stick_settings, action) else {
+            continue;
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
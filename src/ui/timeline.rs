@@ -0,0 +1,105 @@
+// Copyright (C) 2023 by Tristan de Cacqueray
+// SPDX-License-Identifier: MIT
+
+//! This module contains the timeline panel of the TAS mode.
+
+use bevy::prelude::*;
+
+use crate::resources::{GameResources, Tas};
+
+use super::button::TEXT_COLOR;
+
+/// The number of frames displayed around the selection.
+const ROWS: usize = 8;
+
+#[derive(Component)]
+pub struct Timeline;
+
+#[derive(Component)]
+pub struct TimelineText;
+
+pub fn spawn(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(60.0),
+                    left: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                ..default()
+            },
+            Timeline,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                ),
+                TimelineText,
+            ));
+        });
+}
+
+pub fn despawn(mut commands: Commands, query: Query<Entity, With<Timeline>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn update(
+    state: Res<GameResources>,
+    tas: Res<Tas>,
+    mut query: Query<&mut Text, With<TimelineText>>,
+) {
+    let mut lines = vec![format!(
+        "TAS frame {}/{}",
+        state.frame_count,
+        tas.inputs.len()
+    )];
+    let first = tas.selected.saturating_sub(ROWS / 2);
+    for frame in first..=(first + ROWS).min(tas.inputs.len()) {
+        let selected = if frame == tas.selected { ">" } else { " " };
+        let thrust = match tas.inputs.get(frame) {
+            Some(thrust) => describe(*thrust),
+            None => "new".into(),
+        };
+        let current = if frame == state.frame_count {
+            " <- ship"
+        } else {
+            ""
+        };
+        lines.push(format!("{}{:>5} {}{}", selected, frame, thrust, current));
+    }
+    lines.push("n: step  b: back  [ ]: select".into());
+    lines.push("j: jump  e: set the held thrust".into());
+    query.single_mut().sections[0].value = lines.join("\n");
+}
+
+/// The keyboard directions are displayed with arrows, the stick thrusts with their values.
+fn describe(thrust: Vec2) -> String {
+    let digital = |value: f32, neg: &'static str, pos: &'static str| {
+        if value == -1.0 {
+            Some(neg)
+        } else if value == 0.0 {
+            Some("")
+        } else if value == 1.0 {
+            Some(pos)
+        } else {
+            None
+        }
+    };
+    match (digital(thrust.x, "<", ">"), digital(thrust.y, "v", "^")) {
+        (Some(""), Some("")) => ".".into(),
+        (Some(x), Some(y)) => format!("{}{}", x, y),
+        _ => format!("{:+.2} {:+.2}", thrust.x, thrust.y),
+    }
+}